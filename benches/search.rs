@@ -0,0 +1,134 @@
+//! Search latency over a synthetic 10k-entry catalog. Every keystroke searches
+//! the catalog before the next frame is drawn, so even on catalogs far larger
+//! than a typical desktop's, a keystroke should stay within a 16 ms frame.
+//!
+//! Run with `cargo bench --bench search`.
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use std::hint::black_box;
+
+#[allow(dead_code, unused_imports)]
+#[path = "../src/search.rs"]
+mod search;
+
+use search::{SearchFields, SearchIndex};
+
+const CATALOG_SIZE: usize = 10_000;
+
+/// Below this many matches the UI also looks for typo-tolerant matches
+const FEW_RESULTS: usize = 3;
+
+const WORDS: &[&str] = &[
+    "Audio", "Browser", "Calendar", "Chat", "Clock", "Code", "Color", "Disk", "Document", "Draw",
+    "Editor", "Email", "Files", "Font", "Game", "Graph", "Image", "Manager", "Map", "Media",
+    "Monitor", "Music", "Network", "Notes", "Office", "Paint", "Password", "Photo", "Player",
+    "Printer", "Reader", "Recorder", "Screen", "Settings", "Shell", "Sound", "Studio", "System",
+    "Terminal", "Text", "Tracker", "Video", "Viewer", "Web", "Writer",
+];
+
+struct Entry {
+    name: String,
+    generic_name: String,
+    keywords: Vec<String>,
+    description: String,
+}
+
+/// Deterministic catalog of multi-word names with every secondary field set
+fn catalog() -> Vec<Entry> {
+    let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut word = move || {
+        seed = seed
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        WORDS[(seed >> 33) as usize % WORDS.len()]
+    };
+
+    (0..CATALOG_SIZE)
+        .map(|i| Entry {
+            name: format!("{}{} {} {}", word(), word().to_lowercase(), word(), i),
+            generic_name: format!("{} {}", word(), word()),
+            keywords: (0..3).map(|_| word().to_string()).collect(),
+            description: format!(
+                "Open, edit and share {} {} with a {} {}",
+                word().to_lowercase(),
+                word().to_lowercase(),
+                word().to_lowercase(),
+                word().to_lowercase()
+            ),
+        })
+        .collect()
+}
+
+fn build_index(catalog: &[Entry]) -> SearchIndex {
+    SearchIndex::new(
+        catalog.iter().map(|entry| SearchFields {
+            name: &entry.name,
+            generic_name: Some(&entry.generic_name),
+            keywords: &entry.keywords,
+            description: Some(&entry.description),
+            aliases: &[],
+        }),
+        true,
+    )
+}
+
+/// The UI's search for one keystroke, without the launch history boost.
+/// Returns the exact matches, best first, which the next keystroke can refine.
+fn search(index: &SearchIndex, tokens: &[Vec<char>], candidates: Option<&[usize]>) -> Vec<usize> {
+    let mut matches = index.matches(tokens, candidates);
+    matches.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+    let exact: Vec<usize> = matches.into_iter().map(|(index, _)| index).collect();
+
+    if exact.len() < FEW_RESULTS {
+        black_box(index.approximate(tokens, &exact));
+    }
+    exact
+}
+
+fn bench_search(c: &mut Criterion) {
+    let catalog = catalog();
+    c.bench_function("index_10k", |b| b.iter(|| build_index(black_box(&catalog))));
+
+    let index = build_index(&catalog);
+
+    // Whole-catalog search, as after deleting a character or pasting a query
+    let mut group = c.benchmark_group("keystroke_10k");
+    for query in ["t", "term", "term shell", "photo viewer 42", "termnial"] {
+        let tokens = index.tokens(query);
+        group.bench_with_input(BenchmarkId::from_parameter(query), &tokens, |b, tokens| {
+            b.iter(|| search(&index, black_box(tokens), None))
+        });
+    }
+    group.finish();
+
+    // Typing a query one character at a time, each keystroke refining the last
+    let mut group = c.benchmark_group("typing_10k");
+    for query in ["music player", "doc writer 9"] {
+        let prefixes: Vec<Vec<Vec<char>>> = query
+            .char_indices()
+            .map(|(i, c)| index.tokens(&query[..i + c.len_utf8()]))
+            .collect();
+
+        group.throughput(Throughput::Elements(prefixes.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(query),
+            &prefixes,
+            |b, prefixes| {
+                b.iter(|| {
+                    let mut previous: &[Vec<char>] = &[];
+                    let mut results = Vec::new();
+                    for tokens in prefixes {
+                        let candidates = search::refines(previous, tokens).then_some(&results[..]);
+                        results = search(&index, tokens, candidates);
+                        previous = tokens;
+                    }
+                    results
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_search);
+criterion_main!(benches);
@@ -1,5 +1,8 @@
+use crate::config::IconConfig;
+use serde::{Deserialize, Serialize};
 use std::error::Error;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::{env, fs};
 
 #[path = "platforms/windows.rs"]
 #[cfg(windows)]
@@ -13,15 +16,87 @@ mod macos;
 #[cfg(target_os = "linux")]
 mod linux;
 
+/// Size in logical pixels at which the launcher draws icons
+pub const ICON_SIZE: u32 = 48;
+
 /// Simplified application information
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct AppInfo {
+    /// Stable identifier used to deduplicate entries (desktop-file ID on Linux)
+    pub id: String,
     /// Display name of the application
     pub name: String,
+    /// Generic description of the kind of application, e.g. "Web Browser"
+    pub generic_name: Option<String>,
+    /// Extra search terms, e.g. "Internet" or "WWW"
+    pub keywords: Vec<String>,
+    /// Tooltip-style description of the application
+    pub description: Option<String>,
     /// Path to the main executable
     pub exe_path: PathBuf,
+    /// Arguments passed to the executable on launch
+    pub args: Vec<String>,
     /// Optional path to icon file
     pub icon_path: Option<PathBuf>,
+    /// Whether the application must run inside a terminal emulator
+    pub terminal: bool,
+    /// Secondary launch targets such as "New Private Window"
+    pub actions: Vec<AppAction>,
+}
+
+/// Additional way to launch an application (a desktop entry action on Linux)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AppAction {
+    /// Display name of the action
+    pub name: String,
+    /// Path to the executable run by the action
+    pub exe_path: PathBuf,
+    /// Arguments passed to the executable on launch
+    pub args: Vec<String>,
+    /// Optional path to the action's own icon file
+    pub icon_path: Option<PathBuf>,
+}
+
+/// Built-in action of the operating system such as "Shutdown" or "Lock Screen"
+#[derive(Debug, Clone, PartialEq)]
+pub struct SystemCommand {
+    pub name: String,
+    /// Program run directly, without a shell
+    pub program: PathBuf,
+    pub args: Vec<String>,
+    pub icon_path: Option<PathBuf>,
+}
+
+/// Desktop entry left out of discovery, with the reason it was excluded
+#[derive(Debug, Clone)]
+pub struct ExcludedEntry {
+    /// Identifier the entry would have had (desktop-file ID on Linux)
+    pub id: String,
+    /// Location of the entry on disk
+    pub path: PathBuf,
+    /// Human-readable explanation, e.g. "NoDisplay=true"
+    pub reason: String,
+}
+
+/// Catalog entry added, updated or removed after its files changed on disk
+#[cfg(target_os = "linux")]
+#[derive(Debug)]
+pub enum CatalogChange {
+    Added(AppInfo),
+    Updated(AppInfo),
+    Removed(AppInfo),
+}
+
+#[cfg(target_os = "linux")]
+impl std::fmt::Display for CatalogChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (event, app) = match self {
+            CatalogChange::Added(app) => ("Added", app),
+            CatalogChange::Updated(app) => ("Updated", app),
+            CatalogChange::Removed(app) => ("Removed", app),
+        };
+        write!(f, "{}: {} [{}]", event, app.name, app.id)
+    }
 }
 
 pub fn discover_applications() -> Result<Vec<AppInfo>, Box<dyn Error>> {
@@ -41,6 +116,77 @@ pub fn discover_applications() -> Result<Vec<AppInfo>, Box<dyn Error>> {
     }
 }
 
+/// Shutdown, restart, logout and similar commands of the current platform,
+/// with icons looked up in the configured theme
+pub fn system_commands(icons: &IconConfig) -> Vec<SystemCommand> {
+    #[cfg(windows)]
+    {
+        windows::system_commands(icons)
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        macos::system_commands(icons)
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        linux::system_commands(icons)
+    }
+}
+
+/// Entries skipped during discovery. Only Linux desktop entries can be excluded.
+pub fn discover_excluded_entries() -> Vec<ExcludedEntry> {
+    #[cfg(target_os = "linux")]
+    {
+        linux::discover_excluded_entries()
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        Vec::new()
+    }
+}
+
+/// Directories to watch for changes to the catalog (Linux only)
+#[cfg(target_os = "linux")]
+pub fn watch_directories() -> Vec<PathBuf> {
+    linux::watch_directories()
+}
+
+/// Update `apps` for the changed `paths`, returning what was added, updated or removed
+#[cfg(target_os = "linux")]
+pub fn apply_changes(apps: &mut Vec<AppInfo>, paths: &[PathBuf]) -> Vec<CatalogChange> {
+    linux::apply_changes(apps, paths)
+}
+
+/// Print discovered applications, or the excluded entries when `show_hidden` is set
+pub fn list(show_hidden: bool, show_reason: bool) -> Result<(), String> {
+    if show_hidden {
+        let mut excluded = discover_excluded_entries();
+        excluded.sort_by(|a, b| a.id.cmp(&b.id));
+
+        for entry in &excluded {
+            if show_reason {
+                println!("{:<48} {}", entry.id, entry.reason);
+            } else {
+                println!("{:<48} {}", entry.id, entry.path.display());
+            }
+        }
+        println!("{} excluded entries", excluded.len());
+    } else {
+        let mut apps = discover_applications().map_err(|e| e.to_string())?;
+        apps.sort_by_key(|app| app.name.to_lowercase());
+
+        for app in &apps {
+            println!("{:<48} {}", app.name, app.id);
+        }
+        println!("{} applications", apps.len());
+    }
+
+    Ok(())
+}
+
 pub fn extract_icon(app: &AppInfo) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
     #[cfg(windows)]
     {
@@ -57,3 +203,30 @@ pub fn extract_icon(app: &AppInfo) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
         linux::extract_icon(app)
     }
 }
+
+/// Locate an executable file by absolute path or on `PATH`
+pub fn find_executable(command: &str) -> Option<PathBuf> {
+    let is_executable = |path: &Path| {
+        fs::metadata(path).is_ok_and(|metadata| {
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+            }
+
+            #[cfg(not(unix))]
+            {
+                metadata.is_file()
+            }
+        })
+    };
+
+    if Path::new(command).is_absolute() {
+        let path = PathBuf::from(command);
+        return is_executable(&path).then_some(path);
+    }
+
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(command))
+        .find(|path| is_executable(path))
+}
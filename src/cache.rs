@@ -0,0 +1,353 @@
+use crate::APP_NAME;
+use crate::apps::{self, AppInfo};
+use crate::icons;
+use crate::logs;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Bumped whenever the layout or the meaning of cached entries changes
+const FORMAT_VERSION: u32 = 3;
+
+/// Parsed desktop entries from an earlier scan, keyed by source file path.
+/// Stored as JSON in the cache directory.
+#[derive(Serialize, Deserialize)]
+pub struct DiscoveryCache {
+    /// `FORMAT_VERSION` of the code that wrote the cache
+    version: u32,
+    /// Environment the entries were resolved in (locale, desktops, PATH, icon themes)
+    context: String,
+    entries: HashMap<PathBuf, CachedEntry>,
+}
+
+/// Outcome of parsing one desktop entry file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedEntry {
+    /// Modification time of the source file, in nanoseconds since the Unix epoch
+    pub mtime: u64,
+    /// `TryExec` binary and whether it was installed when the entry was parsed
+    pub try_exec: Option<(String, bool)>,
+    /// The visible application, or why the entry was excluded
+    pub result: Result<AppInfo, String>,
+    /// Icon files the application and its actions were resolved to, with the
+    /// modification time of the folder each one is in
+    icons: Vec<(PathBuf, Option<u64>)>,
+}
+
+impl CachedEntry {
+    pub fn new(
+        mtime: u64,
+        try_exec: Option<(String, bool)>,
+        result: Result<AppInfo, String>,
+    ) -> Self {
+        let icons = match &result {
+            Ok(app) => app
+                .icon_path
+                .iter()
+                .chain(
+                    app.actions
+                        .iter()
+                        .filter_map(|action| action.icon_path.as_ref()),
+                )
+                .map(|path| (path.clone(), path.parent().and_then(self::mtime)))
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+
+        Self {
+            mtime,
+            try_exec,
+            result,
+            icons,
+        }
+    }
+
+    /// Whether the resolved icons are all still in place. Installing or removing
+    /// icons changes the mtime of their folder, which may make a different file
+    /// the best match, so the entry is resolved again.
+    pub fn icons_current(&self) -> bool {
+        self.icons.iter().all(|(path, folder_mtime)| {
+            path.exists() && path.parent().and_then(mtime) == *folder_mtime
+        })
+    }
+}
+
+impl DiscoveryCache {
+    pub fn new(context: String) -> Self {
+        Self {
+            version: FORMAT_VERSION,
+            context,
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Load the cache written for `context`. Starts empty when the file is missing,
+    /// unreadable, or was written in a different environment.
+    pub fn load(context: String) -> Self {
+        let mut cache = Self::new(context);
+
+        let Some(data) = cache_file().and_then(|path| fs::read(path).ok()) else {
+            return cache;
+        };
+
+        match decode(&data) {
+            Ok(stored) if stored.context == cache.context => cache.entries = stored.entries,
+            Ok(_) => logs::log_info("Discovery cache is stale, rescanning all entries"),
+            Err(e) => logs::log_error(&format!("Ignoring unreadable discovery cache: {}", e)),
+        }
+
+        cache
+    }
+
+    /// Cached entry for `path`, if the file hasn't been modified since it was parsed
+    pub fn get(&self, path: &Path, mtime: u64) -> Option<&CachedEntry> {
+        self.entries.get(path).filter(|entry| entry.mtime == mtime)
+    }
+
+    pub fn insert(&mut self, path: PathBuf, entry: CachedEntry) {
+        self.entries.insert(path, entry);
+    }
+
+    /// Drop entries for files that no longer exist, returning whether there were any
+    pub fn prune(&mut self, seen: &HashSet<PathBuf>) -> bool {
+        let count = self.entries.len();
+        self.entries.retain(|path, _| seen.contains(path));
+        self.entries.len() != count
+    }
+
+    /// Drop entries for files at or below `paths` that no longer exist, returning
+    /// whether there were any
+    pub fn prune_missing(&mut self, paths: &[PathBuf]) -> bool {
+        let count = self.entries.len();
+        self.entries.retain(|path, _| {
+            !paths.iter().any(|changed| path.starts_with(changed)) || path.exists()
+        });
+        self.entries.len() != count
+    }
+
+    /// Write the cache to disk. A temporary file is renamed into place so
+    /// concurrent readers never see a partial cache.
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        let path = cache_file().ok_or("No cache directory available")?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let temp_path = path.with_extension(format!("tmp{}", std::process::id()));
+        fs::write(&temp_path, serde_json::to_vec(self)?)?;
+        fs::rename(&temp_path, &path)?;
+
+        Ok(())
+    }
+}
+
+/// Modification time of `path` in the resolution stored in the cache
+pub fn mtime(path: &Path) -> Option<u64> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    let since_epoch = modified.duration_since(UNIX_EPOCH).ok()?;
+    u64::try_from(since_epoch.as_nanos()).ok()
+}
+
+pub fn cache_file() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join(APP_NAME).join("discovery.cache"))
+}
+
+pub fn clear() -> Result<(), String> {
+    let path = cache_file().ok_or("No cache directory available")?;
+
+    if path.exists() {
+        fs::remove_file(&path).map_err(|e| format!("Failed to remove cache: {}", e))?;
+        println!("Discovery cache cleared");
+    } else {
+        println!("No discovery cache to clear");
+    }
+
+    // Icons are cached under a name derived from their file and mtime, so the
+    // PNGs of changed or removed icons are only ever deleted here
+    if let Some(icons) = icons::disk_cache_dir()
+        && icons.exists()
+    {
+        fs::remove_dir_all(&icons).map_err(|e| format!("Failed to remove icon cache: {}", e))?;
+        println!("Icon cache cleared");
+    }
+
+    Ok(())
+}
+
+/// Discard the cache and scan every entry again, writing a fresh cache
+pub fn rebuild() -> Result<(), String> {
+    let path = cache_file().ok_or("No cache directory available")?;
+    if path.exists() {
+        fs::remove_file(&path).map_err(|e| format!("Failed to remove cache: {}", e))?;
+    }
+
+    let apps = apps::discover_applications().map_err(|e| e.to_string())?;
+    println!("Discovery cache rebuilt: {} applications", apps.len());
+
+    Ok(())
+}
+
+pub fn stats() -> Result<(), String> {
+    let path = cache_file().ok_or("No cache directory available")?;
+
+    let data = match fs::read(&path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            println!("No discovery cache at {}", path.display());
+            return Ok(());
+        }
+        Err(e) => return Err(format!("Failed to read cache: {}", e)),
+    };
+    let cache = decode(&data).map_err(|e| format!("Unreadable cache: {}", e))?;
+
+    let apps = cache
+        .entries
+        .values()
+        .filter(|entry| entry.result.is_ok())
+        .count();
+    let updated = fs::metadata(&path)
+        .and_then(|m| m.modified())
+        .map(|modified| {
+            chrono::DateTime::<chrono::Local>::from(modified)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        })
+        .unwrap_or_else(|_| "unknown".to_string());
+
+    println!("Cache file: {}", path.display());
+    println!("Size:       {:.1} KiB", data.len() as f64 / 1024.0);
+    println!("Updated:    {}", updated);
+    println!(
+        "Entries:    {} ({} applications, {} excluded)",
+        cache.entries.len(),
+        apps,
+        cache.entries.len() - apps
+    );
+
+    Ok(())
+}
+
+fn decode(data: &[u8]) -> io::Result<DiscoveryCache> {
+    /// Read first, so older formats are reported as such rather than as corrupt
+    #[derive(Deserialize)]
+    struct Header {
+        version: u32,
+    }
+
+    let header: Header = serde_json::from_slice(data)?;
+    if header.version != FORMAT_VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Unsupported cache version",
+        ));
+    }
+
+    Ok(serde_json::from_slice(data)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apps::AppAction;
+
+    fn sample() -> DiscoveryCache {
+        let mut cache = DiscoveryCache::new("locale=de_DE;desktops=GNOME".to_string());
+        let app = AppInfo {
+            id: "firefox.desktop".to_string(),
+            name: "Firefox".to_string(),
+            exe_path: PathBuf::from("/usr/bin/firefox"),
+            icon_path: Some(PathBuf::from(
+                "/usr/share/icons/hicolor/48x48/apps/firefox.png",
+            )),
+            actions: vec![AppAction {
+                name: "New Private Window".to_string(),
+                exe_path: PathBuf::from("/usr/bin/firefox"),
+                args: vec!["--private-window".to_string()],
+                icon_path: None,
+            }],
+            ..Default::default()
+        };
+        cache.insert(
+            PathBuf::from("/usr/share/applications/firefox.desktop"),
+            CachedEntry::new(1_700_000_000_000_000_000, None, Ok(app)),
+        );
+        cache.insert(
+            PathBuf::from("/usr/share/applications/mimeinfo.desktop"),
+            CachedEntry::new(
+                42,
+                Some(("mimeinfo".to_string(), false)),
+                Err("TryExec mimeinfo is not installed".to_string()),
+            ),
+        );
+        cache
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let cache = sample();
+        let decoded = decode(&serde_json::to_vec(&cache).unwrap()).expect("cache should decode");
+
+        assert_eq!(decoded.context, cache.context);
+        assert_eq!(decoded.entries, cache.entries);
+    }
+
+    #[test]
+    fn test_corrupt_cache() {
+        let data = serde_json::to_vec(&sample()).unwrap();
+
+        assert!(decode(&data[..data.len() / 2]).is_err());
+        assert!(decode(b"").is_err());
+        assert!(decode(b"LDCACHE\0\0\0\x01").is_err());
+        assert!(decode(br#"{"version":3,"context":"","entries":{"/a":{"mtime":"x"}}}"#).is_err());
+
+        let mut old = sample();
+        old.version = FORMAT_VERSION - 1;
+        let error = decode(&serde_json::to_vec(&old).unwrap()).err().unwrap();
+        assert_eq!(error.to_string(), "Unsupported cache version");
+    }
+
+    #[test]
+    fn test_prune_missing() {
+        let mut cache = sample();
+        let firefox = PathBuf::from("/usr/share/applications/firefox.desktop");
+        let entry = cache.entries[&firefox].clone();
+        cache.insert(std::env::current_exe().unwrap(), entry);
+
+        // Only missing files at or below the changed paths are dropped
+        assert!(!cache.prune_missing(&[PathBuf::from("/nonexistent")]));
+        assert!(cache.prune_missing(&[firefox.clone()]));
+        assert!(!cache.entries.contains_key(&firefox));
+        assert!(cache.prune_missing(&[PathBuf::from("/")]));
+        assert_eq!(cache.entries.len(), 1);
+    }
+
+    #[test]
+    fn test_icons_current() {
+        let dir = std::env::temp_dir().join(format!("launchdock-cache-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let icon = dir.join("app.png");
+        fs::write(&icon, b"").unwrap();
+
+        let app = AppInfo {
+            icon_path: Some(icon.clone()),
+            ..Default::default()
+        };
+        let entry = CachedEntry::new(0, None, Ok(app));
+        assert!(entry.icons_current());
+
+        // Entries without icons have nothing to check
+        assert!(CachedEntry::new(0, None, Ok(AppInfo::default())).icons_current());
+
+        // The folder changed since, e.g. because another icon was installed
+        let mut changed = entry.clone();
+        changed.icons[0].1 = changed.icons[0].1.map(|mtime| mtime - 1);
+        assert!(!changed.icons_current());
+
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(!entry.icons_current());
+    }
+}
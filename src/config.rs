@@ -0,0 +1,166 @@
+use crate::{APP_NAME, logs};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// User configuration read from `config.toml` in the launchdock config directory.
+/// Every section is optional; missing keys fall back to their defaults.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub terminal: TerminalConfig,
+    pub icons: IconConfig,
+    pub empty_query: EmptyQueryConfig,
+    pub search: SearchConfig,
+    pub providers: ProvidersConfig,
+    pub files: FilesConfig,
+    /// Extra names to find apps by, keyed by app ID as printed by `launchdock list`
+    pub aliases: HashMap<String, Vec<String>>,
+}
+
+/// Terminal emulator used for entries with `Terminal=true`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct TerminalConfig {
+    /// Terminal emulator to run, e.g. "kitty"; detected automatically when unset
+    pub command: Option<String>,
+    /// Arguments placed between the terminal and the wrapped command, e.g. ["-e"];
+    /// defaults to the known convention for the configured terminal
+    pub args: Option<Vec<String>>,
+}
+
+/// Icon theme used to resolve application icons
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct IconConfig {
+    /// Icon theme name, e.g. "Papirus-Dark"; read from the desktop settings when unset
+    pub theme: Option<String>,
+    /// Integer scale factor of the display; taken from `GDK_SCALE` or
+    /// `QT_SCALE_FACTOR` when unset
+    pub scale: Option<u32>,
+    /// Keep rasterized icons as PNGs in the cache directory across launches
+    pub disk_cache: bool,
+}
+
+/// What the launcher lists before anything is typed
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct EmptyQueryConfig {
+    /// Show apps pinned from the UI with Ctrl+P
+    pub favorites: bool,
+    /// Show recently launched apps after the favorites
+    pub recent: bool,
+}
+
+impl Default for EmptyQueryConfig {
+    fn default() -> Self {
+        Self {
+            favorites: true,
+            recent: true,
+        }
+    }
+}
+
+/// How queries are matched against application names
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SearchConfig {
+    /// Ignore accents and other diacritics, so "ecran" finds "Écran"
+    pub strip_diacritics: bool,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            strip_diacritics: true,
+        }
+    }
+}
+
+/// Executables in the `providers` directory next to `config.toml`, run for
+/// every query to add their own results
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ProvidersConfig {
+    /// How long a script may take to answer before it is killed, in milliseconds
+    pub timeout_ms: u64,
+}
+
+impl Default for ProvidersConfig {
+    fn default() -> Self {
+        Self { timeout_ms: 1000 }
+    }
+}
+
+/// Files and folders searched by starting the query with a prefix
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct FilesConfig {
+    /// Query prefixes that search files instead of apps, e.g. "/" or "f "
+    pub prefixes: Vec<String>,
+    /// Directories indexed with everything below them; `~` stands for the home
+    /// directory. None by default, which turns file search off.
+    pub roots: Vec<PathBuf>,
+    /// Paths left out of the index, as `.gitignore` patterns relative to each root
+    pub exclude: Vec<String>,
+}
+
+impl Default for FilesConfig {
+    fn default() -> Self {
+        Self {
+            prefixes: vec!["/".to_string(), "f ".to_string()],
+            roots: Vec::new(),
+            exclude: [".*", "node_modules/", "target/", "__pycache__/"]
+                .map(String::from)
+                .to_vec(),
+        }
+    }
+}
+
+impl FilesConfig {
+    /// Configured roots with `~` expanded to the home directory
+    pub fn root_paths(&self) -> Vec<PathBuf> {
+        self.roots
+            .iter()
+            .filter_map(|root| expand_home(root))
+            .collect()
+    }
+}
+
+/// Replace a leading `~` with the home directory; None if it is unknown
+fn expand_home(path: &Path) -> Option<PathBuf> {
+    match path.strip_prefix("~") {
+        Ok(rest) if rest.as_os_str().is_empty() => dirs::home_dir(),
+        Ok(rest) => dirs::home_dir().map(|home| home.join(rest)),
+        Err(_) => Some(path.to_path_buf()),
+    }
+}
+
+impl Config {
+    /// Load the configuration file, falling back to defaults if it is missing or invalid
+    pub fn load() -> Config {
+        let Some(path) = config_file() else {
+            return Config::default();
+        };
+
+        match std::fs::read_to_string(&path) {
+            Ok(content) => toml::from_str(&content).unwrap_or_else(|e| {
+                logs::log_error(&format!("Invalid config {}: {}", path.display(), e));
+                Config::default()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Config::default(),
+            Err(e) => {
+                logs::log_error(&format!("Failed to read {}: {}", path.display(), e));
+                Config::default()
+            }
+        }
+    }
+}
+
+pub fn config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(APP_NAME))
+}
+
+fn config_file() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("config.toml"))
+}
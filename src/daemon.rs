@@ -1,16 +1,31 @@
+use crate::apps::{self, AppInfo};
+use crate::config::Config;
+use crate::files::{FileIndex, FileMatch};
 use crate::logs;
+#[cfg(target_os = "linux")]
+use crate::watcher;
 use std::env;
 use std::io::Read;
 use std::net::{TcpListener, TcpStream};
+#[cfg(target_os = "linux")]
+use std::path::PathBuf;
 use std::process::{Child, Command, Stdio};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
+use std::time::{Duration, Instant};
 
 use crate::ipc::{
-    Command as IpcCommand, DAEMON_ADDR, Response, messages, pid_file_path, send_command,
-    send_response,
+    Command as IpcCommand, DAEMON_ADDR, Response, TOKEN_LEN, create_token, messages, pid_file_path,
+    read_file_query, send_command, send_file_query, send_response, token_file_path,
 };
 
+/// How long a client may take to send its request or read the response
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How often the file index is rebuilt where changes aren't watched
+#[cfg(not(target_os = "linux"))]
+const FILE_RESCAN_INTERVAL: std::time::Duration = std::time::Duration::from_secs(600);
+
 // Public API functions that main.rs calls
 
 pub fn start() -> Result<(), String> {
@@ -102,6 +117,26 @@ pub fn status() -> Result<(), String> {
     }
 }
 
+/// Fetch the application catalog held by the running daemon
+pub fn catalog() -> Result<Vec<AppInfo>, String> {
+    match send_command(IpcCommand::Catalog) {
+        Ok(Response::Catalog(apps)) => Ok(apps),
+        Ok(Response::Error(e)) => Err(e),
+        Ok(_) => Err(messages::INVALID_RESPONSE.to_string()),
+        Err(e) => Err(format!("{}: {}", messages::FAILED_TO_COMMUNICATE, e)),
+    }
+}
+
+/// Search the file index held by the running daemon
+pub fn search_files(query: &str, limit: usize) -> Result<Vec<FileMatch>, String> {
+    match send_file_query(query, limit) {
+        Ok(Response::Files(files)) => Ok(files),
+        Ok(Response::Error(e)) => Err(e),
+        Ok(_) => Err(messages::INVALID_RESPONSE.to_string()),
+        Err(e) => Err(format!("{}: {}", messages::FAILED_TO_COMMUNICATE, e)),
+    }
+}
+
 pub fn is_running() -> bool {
     let pid_path = pid_file_path();
     if let Ok(pid_str) = std::fs::read_to_string(&pid_path) {
@@ -125,19 +160,28 @@ pub fn is_running() -> bool {
 struct DaemonState {
     ui_process: Option<Child>,
     ui_visible: bool,
+    /// Applications discovered at startup and kept current by the watcher,
+    /// handed to each UI process
+    catalog: Vec<AppInfo>,
 }
 
+/// Files below the configured roots; None until the first scan is done. Kept
+/// apart from `DaemonState`, as searching many files takes a while.
+type SharedFiles = Arc<RwLock<Option<FileIndex>>>;
+
 enum Message {
     ShowUI,
     CheckStatus,
+    GetCatalog,
     Shutdown,
 }
 
 impl DaemonState {
-    fn new() -> Self {
+    fn new(catalog: Vec<AppInfo>) -> Self {
         Self {
             ui_process: None,
             ui_visible: false,
+            catalog,
         }
     }
 
@@ -154,6 +198,7 @@ impl DaemonState {
                 daemon_running: true,
                 ui_visible: self.ui_visible,
             },
+            Message::GetCatalog => Response::Catalog(self.catalog.clone()),
             Message::Shutdown => {
                 if let Some(mut child) = self.ui_process.take() {
                     let _ = child.kill();
@@ -211,7 +256,26 @@ impl DaemonState {
     }
 }
 
-fn handle_client(mut stream: TcpStream, state: Arc<Mutex<DaemonState>>) -> bool {
+fn handle_client(
+    mut stream: TcpStream,
+    state: Arc<Mutex<DaemonState>>,
+    files: &SharedFiles,
+    token: &[u8],
+) -> bool {
+    // A client that stops sending mustn't hold up everyone else
+    if stream.set_read_timeout(Some(CLIENT_TIMEOUT)).is_err()
+        || stream.set_write_timeout(Some(CLIENT_TIMEOUT)).is_err()
+    {
+        return false;
+    }
+
+    // Only processes that could read the token file are answered
+    let mut client_token = [0u8; TOKEN_LEN];
+    if stream.read_exact(&mut client_token).is_err() || client_token[..] != *token {
+        logs::log_error("Rejected a client without the daemon token");
+        return false;
+    }
+
     // Read single byte command
     let mut cmd_byte = [0u8; 1];
     if stream.read_exact(&mut cmd_byte).is_err() {
@@ -224,23 +288,32 @@ fn handle_client(mut stream: TcpStream, state: Arc<Mutex<DaemonState>>) -> bool
         return false;
     };
 
+    let message = match cmd {
+        IpcCommand::Stop => {
+            logs::log_info("Received stop command");
+            Message::Shutdown
+        }
+        IpcCommand::Show => {
+            logs::log_info("Received show command");
+            Message::ShowUI
+        }
+        IpcCommand::Status => Message::CheckStatus,
+        IpcCommand::Catalog => Message::GetCatalog,
+        // Searching many files takes a while, so it's done on a thread of its
+        // own, from the file index without locking the daemon state
+        IpcCommand::Files => {
+            let files = Arc::clone(files);
+            thread::spawn(move || answer_file_query(stream, &files));
+            return false;
+        }
+    };
+    let should_exit = matches!(message, Message::Shutdown);
+
     // Process command and generate response
-    let (response, should_exit) = {
+    let response = {
         let mut state = state.lock().unwrap();
         state.poll_ui_status();
-
-        match cmd {
-            IpcCommand::Stop => {
-                logs::log_info("Received stop command");
-                let resp = state.update(Message::Shutdown);
-                (resp, true) // Signal to exit
-            }
-            IpcCommand::Show => {
-                logs::log_info("Received show command");
-                (state.update(Message::ShowUI), false)
-            }
-            IpcCommand::Status => (state.update(Message::CheckStatus), false),
-        }
+        state.update(message)
     };
 
     // Send response
@@ -249,6 +322,39 @@ fn handle_client(mut stream: TcpStream, state: Arc<Mutex<DaemonState>>) -> bool
     should_exit
 }
 
+/// Update the catalog for changed desktop files and icons. The changes are
+/// worked out on a copy, as reading the applications directories takes a while,
+/// and swapped in under a short lock; only the watcher changes the catalog.
+#[cfg(target_os = "linux")]
+fn refresh_catalog(state: &Mutex<DaemonState>, paths: &[PathBuf]) {
+    let mut catalog = state.lock().unwrap().catalog.clone();
+    let changes = apps::apply_changes(&mut catalog, paths);
+    if changes.is_empty() {
+        return;
+    }
+
+    state.lock().unwrap().catalog = catalog;
+    for change in changes {
+        logs::log_info(&change.to_string());
+    }
+}
+
+/// Read the query following a `Files` command and send the best matches
+fn answer_file_query(mut stream: TcpStream, files: &RwLock<Option<FileIndex>>) {
+    let response = match read_file_query(&mut stream) {
+        Ok((query, limit)) => Response::Files(
+            files
+                .read()
+                .unwrap()
+                .as_ref()
+                .map(|files| files.search(&query, limit))
+                .unwrap_or_default(),
+        ),
+        Err(e) => Response::Error(e.to_string()),
+    };
+    let _ = send_response(&mut stream, &response);
+}
+
 pub fn run_daemon_process() {
     logs::log_info("Daemon process starting");
 
@@ -259,7 +365,39 @@ pub fn run_daemon_process() {
         return;
     }
 
-    let state = Arc::new(Mutex::new(DaemonState::new()));
+    // Start TCP listener
+    let listener = match TcpListener::bind(DAEMON_ADDR) {
+        Ok(l) => l,
+        Err(e) => {
+            eprintln!("Failed to bind to {}: {}", DAEMON_ADDR, e);
+            let _ = std::fs::remove_file(&pid_path);
+            return;
+        }
+    };
+
+    // Written once the port is ours, so a daemon already running keeps its token
+    let token = match create_token() {
+        Ok(token) => token,
+        Err(e) => {
+            eprintln!("Failed to write token file: {}", e);
+            let _ = std::fs::remove_file(&pid_path);
+            return;
+        }
+    };
+
+    // Scan once; clients connecting meanwhile wait in the listen backlog
+    let catalog = match apps::discover_applications() {
+        Ok(apps) => {
+            logs::log_info(&format!("Found {} applications", apps.len()));
+            apps
+        }
+        Err(e) => {
+            logs::log_error(&format!("Application discovery failed: {}", e));
+            Vec::new()
+        }
+    };
+
+    let state = Arc::new(Mutex::new(DaemonState::new(catalog)));
 
     // Start UI status monitor thread
     let monitor_state = Arc::clone(&state);
@@ -271,20 +409,27 @@ pub fn run_daemon_process() {
         }
     });
 
-    // Start TCP listener
-    let listener = match TcpListener::bind(DAEMON_ADDR) {
-        Ok(l) => l,
-        Err(e) => {
-            eprintln!("Failed to bind to {}: {}", DAEMON_ADDR, e);
-            let _ = std::fs::remove_file(&pid_path);
-            return;
-        }
-    };
+    // Keep the catalog in sync with installed and removed applications
+    #[cfg(target_os = "linux")]
+    {
+        let watch_state = Arc::clone(&state);
+        watcher::spawn(
+            "application",
+            apps::watch_directories(),
+            |_| false,
+            move |paths| refresh_catalog(&watch_state, &paths),
+        );
+    }
+
+    // Index files in the background; file searches find nothing until it's done
+    let files: SharedFiles = Arc::new(RwLock::new(None));
+    let indexed_files = Arc::clone(&files);
+    thread::spawn(move || index_files(indexed_files));
 
     // Main daemon loop
     for stream in listener.incoming() {
         if let Ok(stream) = stream
-            && handle_client(stream, Arc::clone(&state))
+            && handle_client(stream, Arc::clone(&state), &files, &token)
         {
             break; // Stop command received
         }
@@ -293,4 +438,59 @@ pub fn run_daemon_process() {
     // Cleanup
     logs::log_info("Daemon process shutting down");
     let _ = std::fs::remove_file(&pid_path);
+    let _ = std::fs::remove_file(token_file_path());
+}
+
+fn build_file_index(config: &Config) -> FileIndex {
+    let start = Instant::now();
+    let index = FileIndex::build(&config.files, config.search.strip_diacritics);
+    logs::log_info(&format!(
+        "Indexed {} files and folders in {:.1?}",
+        index.len(),
+        start.elapsed()
+    ));
+    index
+}
+
+/// Build the file index, then keep it current from inotify events
+#[cfg(target_os = "linux")]
+fn index_files(files: SharedFiles) {
+    let config = Config::load();
+    if config.files.roots.is_empty() {
+        return;
+    }
+
+    let index = build_file_index(&config);
+    let filter = index.filter().clone();
+    *files.write().unwrap() = Some(index);
+
+    // Excluded folders aren't watched, as they are often large and busy
+    let skip_filter = filter.clone();
+    watcher::spawn(
+        "file",
+        filter.roots().to_vec(),
+        move |dir| !skip_filter.includes(dir, true),
+        move |paths| {
+            // Changed folders are read before locking, so searches don't wait on it
+            let changes = filter.scan_changes(paths);
+            if let Some(index) = files.write().unwrap().as_mut() {
+                index.apply_changes(changes);
+            }
+        },
+    );
+}
+
+/// Build the file index, then rebuild it every `FILE_RESCAN_INTERVAL`
+#[cfg(not(target_os = "linux"))]
+fn index_files(files: SharedFiles) {
+    let config = Config::load();
+    if config.files.roots.is_empty() {
+        return;
+    }
+
+    loop {
+        let index = build_file_index(&config);
+        *files.write().unwrap() = Some(index);
+        thread::sleep(FILE_RESCAN_INTERVAL);
+    }
 }
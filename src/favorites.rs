@@ -0,0 +1,70 @@
+use crate::APP_NAME;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+/// App IDs pinned from the UI, listed first when the query is empty
+#[derive(Debug, Default)]
+pub struct Favorites {
+    /// In the order they were pinned
+    ids: Vec<String>,
+}
+
+impl Favorites {
+    /// Load the pinned apps. Starts empty when the file is missing or unreadable.
+    pub fn load() -> Self {
+        let ids = favorites_file()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|content| {
+                content
+                    .lines()
+                    .map(str::trim)
+                    .filter(|id| !id.is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self { ids }
+    }
+
+    pub fn ids(&self) -> &[String] {
+        &self.ids
+    }
+
+    pub fn contains(&self, app_id: &str) -> bool {
+        self.ids.iter().any(|id| id == app_id)
+    }
+
+    /// Pin the app, or unpin it if already pinned. Returns whether it is now pinned.
+    pub fn toggle(&mut self, app_id: &str) -> bool {
+        if self.contains(app_id) {
+            self.ids.retain(|id| id != app_id);
+            false
+        } else {
+            self.ids.push(app_id.to_string());
+            true
+        }
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        let path = favorites_file().ok_or("No data directory available")?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let content: String = self.ids.iter().map(|id| format!("{}\n", id)).collect();
+
+        // Rename a complete file into place so a concurrent UI never reads a partial one
+        let temp_path = path.with_extension(format!("tmp{}", std::process::id()));
+        fs::write(&temp_path, content)?;
+        fs::rename(&temp_path, &path)?;
+
+        Ok(())
+    }
+}
+
+/// One pinned app ID per line
+pub fn favorites_file() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join(APP_NAME).join("favorites"))
+}
@@ -0,0 +1,538 @@
+use crate::config::FilesConfig;
+use crate::search::{SearchFields, SearchIndex};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File or folder matching a query, as sent to the launcher
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileMatch {
+    pub path: PathBuf,
+    pub is_dir: bool,
+    pub score: f32,
+    /// Matched character positions in the file name
+    pub name_matches: Vec<usize>,
+}
+
+/// Which paths below the configured roots are indexed
+#[derive(Debug, Clone)]
+pub struct FileFilter {
+    roots: Vec<PathBuf>,
+    patterns: Vec<ExcludePattern>,
+}
+
+impl FileFilter {
+    pub fn new(config: &FilesConfig) -> Self {
+        // A root inside another one is indexed as part of it
+        let paths = config.root_paths();
+        let mut roots: Vec<PathBuf> = Vec::new();
+        for root in &paths {
+            let nested = paths
+                .iter()
+                .any(|other| other != root && root.starts_with(other));
+            if !nested && !roots.contains(root) {
+                roots.push(root.clone());
+            }
+        }
+
+        Self {
+            roots,
+            patterns: config
+                .exclude
+                .iter()
+                .filter_map(|line| ExcludePattern::parse(line))
+                .collect(),
+        }
+    }
+
+    pub fn roots(&self) -> &[PathBuf] {
+        &self.roots
+    }
+
+    /// Whether `path` is below a root and neither it nor a folder it is in
+    /// is excluded
+    pub fn includes(&self, path: &Path, is_dir: bool) -> bool {
+        let Some(root) = self.roots.iter().find(|root| path.starts_with(root)) else {
+            return false;
+        };
+        let Ok(relative) = path.strip_prefix(root) else {
+            return false;
+        };
+
+        let names: Vec<String> = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        let mut relative = String::new();
+        for (i, name) in names.iter().enumerate() {
+            if !relative.is_empty() {
+                relative.push('/');
+            }
+            relative.push_str(name);
+            if self.excludes(&relative, name, is_dir || i + 1 < names.len()) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Whether the last pattern matching a path, given relative to its root
+    /// with `/` separators, excludes it
+    fn excludes(&self, relative: &str, name: &str, is_dir: bool) -> bool {
+        self.patterns
+            .iter()
+            .rev()
+            .find(|pattern| pattern.matches(relative, name, is_dir))
+            .is_some_and(|pattern| !pattern.negated)
+    }
+
+    /// Everything below `dir` that isn't excluded. Symlinked folders are not
+    /// followed, so link cycles can't recurse forever.
+    fn walk(&self, dir: &Path, found: &mut Vec<FileEntry>) {
+        let Some(relative) = self.relative(dir) else {
+            return;
+        };
+
+        let mut pending = vec![(dir.to_path_buf(), relative)];
+        while let Some((dir, relative)) = pending.pop() {
+            let Ok(entries) = fs::read_dir(&dir) else {
+                continue;
+            };
+
+            for entry in entries.flatten() {
+                let Ok(file_type) = entry.file_type() else {
+                    continue;
+                };
+                // Paths that aren't UTF-8 can't be sent to the launcher
+                let Ok(name) = entry.file_name().into_string() else {
+                    continue;
+                };
+                let entry_relative = if relative.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{}/{}", relative, name)
+                };
+                if self.excludes(&entry_relative, &name, file_type.is_dir()) {
+                    continue;
+                }
+
+                let path = entry.path();
+                if file_type.is_dir() {
+                    pending.push((path.clone(), entry_relative));
+                }
+                found.push(FileEntry {
+                    path,
+                    is_dir: file_type.is_dir(),
+                });
+            }
+        }
+    }
+
+    /// Read the files and folders that were created, changed or removed. A
+    /// changed folder is read again with everything inside, which can take a
+    /// while, so this is done apart from the index.
+    pub fn scan_changes(&self, paths: Vec<PathBuf>) -> FileChanges {
+        let changed: HashSet<&Path> = paths.iter().map(PathBuf::as_path).collect();
+        let is_changed = |path: &Path| path.ancestors().any(|path| changed.contains(path));
+
+        // Anything below another changed path is found by reading that again
+        let mut found = Vec::new();
+        for path in &paths {
+            if path.parent().is_some_and(is_changed) || path.to_str().is_none() {
+                continue;
+            }
+            let Ok(metadata) = fs::symlink_metadata(path) else {
+                continue; // Removed
+            };
+            if !self.includes(path, metadata.is_dir()) {
+                continue;
+            }
+
+            found.push(FileEntry {
+                path: path.clone(),
+                is_dir: metadata.is_dir(),
+            });
+            if metadata.is_dir() {
+                self.walk(path, &mut found);
+            }
+        }
+
+        FileChanges { paths, found }
+    }
+
+    /// Path below its root with `/` separators, empty for the root itself
+    fn relative(&self, path: &Path) -> Option<String> {
+        let root = self.roots.iter().find(|root| path.starts_with(root))?;
+        let relative = path.strip_prefix(root).ok()?;
+        Some(
+            relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/"),
+        )
+    }
+}
+
+/// One line of a `.gitignore`-style exclude list
+#[derive(Debug, Clone)]
+struct ExcludePattern {
+    glob: Vec<char>,
+    /// `!pattern` re-includes what an earlier pattern excluded
+    negated: bool,
+    /// `pattern/` only matches folders
+    dir_only: bool,
+    /// A pattern containing a `/` is matched against the whole path below the
+    /// root, any other against the name of every file and folder
+    anchored: bool,
+}
+
+impl ExcludePattern {
+    /// None for blank lines and `#` comments
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+        let line = line.strip_prefix('/').unwrap_or(line);
+        if line.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            glob: line.chars().collect(),
+            negated,
+            dir_only,
+            anchored,
+        })
+    }
+
+    fn matches(&self, relative: &str, name: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let text: Vec<char> = if self.anchored { relative } else { name }
+            .chars()
+            .collect();
+        glob_match(&self.glob, &text)
+    }
+}
+
+/// Match a glob where `*` and `?` stay within one path component, `**`
+/// crosses components, and `[a-z]` or `[!a-z]` matches one character of a set
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        // Any number of whole folders, including none
+        ['*', '*', '/', rest @ ..] => (0..=text.len())
+            .filter(|&i| i == 0 || text[i - 1] == '/')
+            .any(|i| glob_match(rest, &text[i..])),
+        ['*', '*', rest @ ..] => (0..=text.len()).any(|i| glob_match(rest, &text[i..])),
+        ['*', rest @ ..] => (0..=text.len())
+            .take_while(|&i| i == 0 || text[i - 1] != '/')
+            .any(|i| glob_match(rest, &text[i..])),
+        ['?', rest @ ..] => {
+            matches!(text.first(), Some(&c) if c != '/') && glob_match(rest, &text[1..])
+        }
+        ['[', class @ ..] => match split_class(class) {
+            Some((set, negated, rest)) => text.split_first().is_some_and(|(&c, text)| {
+                c != '/' && class_contains(set, c) != negated && glob_match(rest, text)
+            }),
+            // Without a closing bracket, it's just a character
+            None => text.first() == Some(&'[') && glob_match(class, &text[1..]),
+        },
+        ['\\', c, rest @ ..] | [c, rest @ ..] => {
+            text.first() == Some(c) && glob_match(rest, &text[1..])
+        }
+    }
+}
+
+/// Split what follows a `[` into the set of characters up to the closing
+/// `]`, whether the set is negated by a leading `!` or `^`, and the rest
+fn split_class(class: &[char]) -> Option<(&[char], bool, &[char])> {
+    let (negated, class) = match class {
+        ['!' | '^', class @ ..] => (true, class),
+        class => (false, class),
+    };
+    // A `]` right at the start is part of the set
+    let end = 1 + class.get(1..)?.iter().position(|&c| c == ']')?;
+    Some((&class[..end], negated, &class[end + 1..]))
+}
+
+/// Whether `c` is in a set like `a-z_`
+fn class_contains(set: &[char], c: char) -> bool {
+    let mut i = 0;
+    while i < set.len() {
+        if i + 2 < set.len() && set[i + 1] == '-' {
+            if (set[i]..=set[i + 2]).contains(&c) {
+                return true;
+            }
+            i += 3;
+        } else {
+            if set[i] == c {
+                return true;
+            }
+            i += 1;
+        }
+    }
+    false
+}
+
+/// File or folder found below one of the roots
+struct FileEntry {
+    path: PathBuf,
+    is_dir: bool,
+}
+
+/// Changed paths and what is now at or below them, for `FileIndex::apply_changes`
+pub struct FileChanges {
+    paths: Vec<PathBuf>,
+    found: Vec<FileEntry>,
+}
+
+/// Files and folders below the configured roots, searchable by name. Built
+/// once by walking the roots, then kept current with `apply_changes`.
+pub struct FileIndex {
+    filter: FileFilter,
+    entries: Vec<FileEntry>,
+    /// Names of `entries`, in the same order
+    search_index: SearchIndex,
+}
+
+impl FileIndex {
+    /// Walk every root; slow for large folders, so the daemon builds the index
+    /// in the background
+    pub fn build(config: &FilesConfig, strip_diacritics: bool) -> Self {
+        let filter = FileFilter::new(config);
+        let mut entries = Vec::new();
+        for root in filter.roots() {
+            filter.walk(root, &mut entries);
+        }
+
+        let search_index = SearchIndex::new(
+            entries.iter().map(|entry| search_fields(&entry.path)),
+            strip_diacritics,
+        );
+        Self {
+            filter,
+            entries,
+            search_index,
+        }
+    }
+
+    pub fn filter(&self) -> &FileFilter {
+        &self.filter
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Replace everything at or below the changed paths with what
+    /// `FileFilter::scan_changes` found there
+    pub fn apply_changes(&mut self, changes: FileChanges) {
+        let changed: HashSet<&Path> = changes.paths.iter().map(PathBuf::as_path).collect();
+        let is_changed = |path: &Path| path.ancestors().any(|path| changed.contains(path));
+
+        let keep: Vec<bool> = self
+            .entries
+            .iter()
+            .map(|entry| !is_changed(&entry.path))
+            .collect();
+        let mut index = 0;
+        self.entries.retain(|_| {
+            index += 1;
+            keep[index - 1]
+        });
+        self.search_index.retain(|index| keep[index]);
+
+        for entry in changes.found {
+            self.search_index.push(search_fields(&entry.path));
+            self.entries.push(entry);
+        }
+    }
+
+    /// The best matches by name, shallower paths first among equal scores
+    pub fn search(&self, query: &str, limit: usize) -> Vec<FileMatch> {
+        let tokens = self.search_index.tokens(query);
+        let mut matches = self.search_index.matches(&tokens, None);
+
+        let depth = |index: usize| self.entries[index].path.components().count();
+        let order = |a: &(usize, f32), b: &(usize, f32)| {
+            b.1.total_cmp(&a.1)
+                .then_with(|| depth(a.0).cmp(&depth(b.0)))
+        };
+        // Only the best few need sorting, out of possibly many thousands
+        if matches.len() > limit && limit > 0 {
+            matches.select_nth_unstable_by(limit - 1, order);
+        }
+        matches.truncate(limit);
+        matches.sort_by(order);
+
+        matches
+            .into_iter()
+            .map(|(index, score)| {
+                let entry = &self.entries[index];
+                FileMatch {
+                    path: entry.path.clone(),
+                    is_dir: entry.is_dir,
+                    score,
+                    name_matches: self.search_index.highlight(index, &tokens).name,
+                }
+            })
+            .collect()
+    }
+}
+
+fn search_fields(path: &Path) -> SearchFields<'_> {
+    SearchFields {
+        name: path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default(),
+        generic_name: None,
+        keywords: &[],
+        description: None,
+        aliases: &[],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn excluded(patterns: &[&str], relative: &str, is_dir: bool) -> bool {
+        let filter = FileFilter {
+            roots: vec![PathBuf::from("/home/user")],
+            patterns: patterns
+                .iter()
+                .filter_map(|line| ExcludePattern::parse(line))
+                .collect(),
+        };
+        !filter.includes(&Path::new("/home/user").join(relative), is_dir)
+    }
+
+    #[test]
+    fn test_exclude_patterns() {
+        // Unanchored patterns match a name at any depth, and what's inside
+        assert!(excluded(&["*.log"], "build.log", false));
+        assert!(excluded(&["*.log"], "app/logs/build.log", false));
+        assert!(excluded(
+            &["node_modules/"],
+            "web/node_modules/react/index.js",
+            false
+        ));
+        assert!(!excluded(&["node_modules/"], "notes/node_modules", false));
+        assert!(excluded(&[".*"], ".cache", true));
+        assert!(!excluded(&[".*"], "notes.txt", false));
+
+        // Patterns with a slash are anchored to the root
+        assert!(excluded(&["/Downloads"], "Downloads", true));
+        assert!(!excluded(&["/Downloads"], "music/Downloads", true));
+        assert!(excluded(
+            &["projects/*/target"],
+            "projects/app/target",
+            true
+        ));
+        assert!(!excluded(
+            &["projects/*/target"],
+            "projects/a/b/target",
+            true
+        ));
+        assert!(excluded(
+            &["projects/**/target"],
+            "projects/a/b/target",
+            true
+        ));
+        assert!(excluded(&["**/cache"], "cache", true));
+
+        // The last matching pattern wins
+        assert!(!excluded(&[".*", "!.config"], ".config", true));
+        assert!(excluded(&["!.config", ".*"], ".config", true));
+
+        assert!(excluded(&["photo[0-9].jpg"], "photo7.jpg", false));
+        assert!(!excluded(&["photo[!0-9].jpg"], "photo7.jpg", false));
+        assert!(excluded(&["?.txt"], "a.txt", false));
+        assert!(!excluded(&["# comment", ""], "# comment", false));
+        assert!(!excluded(&[], "notes.txt", false));
+
+        // Paths outside the roots are never indexed
+        assert!(excluded(&[], "/etc/passwd", false));
+    }
+
+    #[test]
+    fn test_file_index() {
+        let dir = std::env::temp_dir().join(format!("launchdock-files-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("projects/launchdock/target")).unwrap();
+        fs::create_dir_all(dir.join(".cache")).unwrap();
+        fs::write(dir.join("projects/launchdock/Cargo.toml"), "").unwrap();
+        fs::write(dir.join("projects/launchdock/target/launchdock"), "").unwrap();
+        fs::write(dir.join(".cache/launchdock.log"), "").unwrap();
+        fs::write(dir.join("report.pdf"), "").unwrap();
+
+        let config = FilesConfig {
+            roots: vec![dir.clone()],
+            ..FilesConfig::default()
+        };
+        let mut index = FileIndex::build(&config, true);
+        let found = |index: &FileIndex, query: &str| -> Vec<PathBuf> {
+            index
+                .search(query, 7)
+                .into_iter()
+                .map(|found| found.path.strip_prefix(&dir).unwrap().to_path_buf())
+                .collect()
+        };
+
+        // Hidden and build folders are left out by default
+        assert_eq!(index.len(), 4);
+        assert_eq!(
+            found(&index, "launchdock"),
+            vec![PathBuf::from("projects/launchdock")]
+        );
+        let matches = index.search("cargo", 7);
+        assert_eq!(matches[0].name_matches, vec![0, 1, 2, 3, 4]);
+        assert!(!matches[0].is_dir);
+
+        // A new folder is added with its contents, and a removed file goes away
+        fs::create_dir_all(dir.join("projects/notes")).unwrap();
+        fs::write(dir.join("projects/notes/launchdock.md"), "").unwrap();
+        fs::remove_file(dir.join("report.pdf")).unwrap();
+        let changes = index.filter().scan_changes(vec![
+            dir.join("projects/notes"),
+            dir.join("projects/notes/launchdock.md"),
+            dir.join("report.pdf"),
+        ]);
+        index.apply_changes(changes);
+        assert_eq!(index.len(), 5);
+        assert_eq!(found(&index, "report"), Vec::<PathBuf>::new());
+        assert_eq!(
+            found(&index, "launchdock"),
+            vec![
+                PathBuf::from("projects/launchdock"),
+                PathBuf::from("projects/notes/launchdock.md")
+            ]
+        );
+
+        // Removing a folder removes everything inside
+        fs::remove_dir_all(dir.join("projects/launchdock")).unwrap();
+        let changes = index
+            .filter()
+            .scan_changes(vec![dir.join("projects/launchdock")]);
+        index.apply_changes(changes);
+        assert_eq!(index.len(), 3);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,291 @@
+use crate::APP_NAME;
+use crate::apps::AppInfo;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Launches kept on disk; older ones are dropped when a new launch is recorded
+const MAX_RECORDS: usize = 2000;
+
+/// Age in seconds after which a launch counts half as much towards frecency
+const HALF_LIFE_SECS: f32 = 7.0 * 24.0 * 60.0 * 60.0;
+
+/// One launch from the UI
+#[derive(Debug, Clone, PartialEq)]
+pub struct LaunchRecord {
+    /// Unix timestamp in seconds
+    pub timestamp: i64,
+    pub app_id: String,
+    /// Display name at launch time, shown by `launchdock history`
+    pub app_name: String,
+    /// Search query typed before launching
+    pub query: String,
+}
+
+/// Launch history with the frecency of every launched app precomputed
+#[derive(Debug, Default)]
+pub struct History {
+    records: Vec<LaunchRecord>,
+    frecency: HashMap<String, f32>,
+}
+
+impl History {
+    /// Build a history, scoring each launch by its age relative to `now`
+    pub fn new(records: Vec<LaunchRecord>, now: i64) -> Self {
+        let mut frecency = HashMap::new();
+        for record in &records {
+            let age = (now - record.timestamp).max(0) as f32;
+            *frecency.entry(record.app_id.clone()).or_insert(0.0) +=
+                0.5f32.powf(age / HALF_LIFE_SECS);
+        }
+
+        Self { records, frecency }
+    }
+
+    /// Load the history file. Starts empty when it is missing or unreadable.
+    pub fn load() -> Self {
+        let records = history_file()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|content| decode(&content))
+            .unwrap_or_default();
+
+        Self::new(records, chrono::Utc::now().timestamp())
+    }
+
+    /// Launch count of an app, with each launch decayed by its age
+    pub fn frecency(&self, app_id: &str) -> f32 {
+        self.frecency.get(app_id).copied().unwrap_or(0.0)
+    }
+
+    /// IDs of launched apps, most recently launched first
+    pub fn recent(&self) -> Vec<&str> {
+        let mut recent: Vec<&str> = Vec::new();
+        for record in self.records.iter().rev() {
+            if !recent.contains(&record.app_id.as_str()) {
+                recent.push(&record.app_id);
+            }
+        }
+        recent
+    }
+
+    /// How often each app was launched from a query sharing a prefix with `query`,
+    /// e.g. "fi" and "fire" both count towards a later search for "fir"
+    pub fn query_choices(&self, query: &str) -> HashMap<&str, usize> {
+        let query = query.to_lowercase();
+        let mut choices = HashMap::new();
+
+        for record in &self.records {
+            let past_query = record.query.to_lowercase();
+            if !past_query.is_empty()
+                && (past_query.starts_with(&query) || query.starts_with(&past_query))
+            {
+                *choices.entry(record.app_id.as_str()).or_insert(0) += 1;
+            }
+        }
+
+        choices
+    }
+}
+
+/// Append a launch to the history file, keeping only the most recent launches
+pub fn record(app: &AppInfo, query: &str) -> Result<(), Box<dyn Error>> {
+    let path = history_file().ok_or("No data directory available")?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut records = match fs::read_to_string(&path) {
+        Ok(content) => decode(&content),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(e.into()),
+    };
+    records.push(LaunchRecord {
+        timestamp: chrono::Utc::now().timestamp(),
+        app_id: app.id.clone(),
+        app_name: app.name.clone(),
+        query: query.to_string(),
+    });
+    let excess = records.len().saturating_sub(MAX_RECORDS);
+
+    // Rename a complete file into place so a concurrent UI never reads a partial one
+    let temp_path = path.with_extension(format!("tmp{}", std::process::id()));
+    fs::write(&temp_path, encode(&records[excess..]))?;
+    fs::rename(&temp_path, &path)?;
+
+    Ok(())
+}
+
+pub fn history_file() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join(APP_NAME).join("history.tsv"))
+}
+
+/// Print launched apps, most frecent first
+pub fn show() -> Result<(), String> {
+    let history = History::load();
+    if history.records.is_empty() {
+        println!("No launch history");
+        return Ok(());
+    }
+
+    // Launch count, latest launch and the name it was launched under, per app
+    let mut apps: HashMap<&str, (usize, &LaunchRecord)> = HashMap::new();
+    for record in &history.records {
+        let entry = apps.entry(&record.app_id).or_insert((0, record));
+        entry.0 += 1;
+        if record.timestamp >= entry.1.timestamp {
+            entry.1 = record;
+        }
+    }
+
+    let mut apps: Vec<_> = apps.into_values().collect();
+    apps.sort_by(|a, b| {
+        history
+            .frecency(&b.1.app_id)
+            .total_cmp(&history.frecency(&a.1.app_id))
+    });
+
+    println!(
+        "{:<32} {:>8} {:>9}  Last launched",
+        "Application", "Launches", "Frecency"
+    );
+    for (count, last) in &apps {
+        let launched = chrono::DateTime::from_timestamp(last.timestamp, 0)
+            .map(|time| {
+                time.with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string()
+            })
+            .unwrap_or_else(|| "unknown".to_string());
+        println!(
+            "{:<32} {:>8} {:>9.2}  {}",
+            last.app_name,
+            count,
+            history.frecency(&last.app_id),
+            launched
+        );
+    }
+    println!(
+        "{} launches of {} applications",
+        history.records.len(),
+        apps.len()
+    );
+
+    Ok(())
+}
+
+pub fn clear() -> Result<(), String> {
+    let path = history_file().ok_or("No data directory available")?;
+
+    if path.exists() {
+        fs::remove_file(&path).map_err(|e| format!("Failed to remove history: {}", e))?;
+        println!("Launch history cleared");
+    } else {
+        println!("No launch history to clear");
+    }
+
+    Ok(())
+}
+
+// One launch per line: timestamp, app ID, app name and query, separated by tabs.
+// Tabs and newlines inside fields are replaced by spaces when writing.
+
+fn decode(content: &str) -> Vec<LaunchRecord> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(4, '\t');
+            Some(LaunchRecord {
+                timestamp: fields.next()?.parse().ok()?,
+                app_id: fields.next()?.to_string(),
+                app_name: fields.next()?.to_string(),
+                query: fields.next()?.to_string(),
+            })
+        })
+        .collect()
+}
+
+fn encode(records: &[LaunchRecord]) -> String {
+    let clean = |field: &str| field.replace(['\t', '\n', '\r'], " ");
+
+    records
+        .iter()
+        .map(|record| {
+            format!(
+                "{}\t{}\t{}\t{}\n",
+                record.timestamp,
+                clean(&record.app_id),
+                clean(&record.app_name),
+                clean(&record.query)
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: i64 = 24 * 60 * 60;
+
+    fn launch(timestamp: i64, app_id: &str, query: &str) -> LaunchRecord {
+        LaunchRecord {
+            timestamp,
+            app_id: app_id.to_string(),
+            app_name: app_id.to_string(),
+            query: query.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_frecency_decays_with_age() {
+        let now = 100 * DAY;
+        let history = History::new(
+            vec![
+                launch(now, "fresh", "f"),
+                launch(now - 7 * DAY, "week", "w"),
+                launch(now - 7 * DAY, "week", "w"),
+                launch(now - 7 * DAY, "week", "w"),
+            ],
+            now,
+        );
+
+        assert!((history.frecency("fresh") - 1.0).abs() < 1e-4);
+        assert!((history.frecency("week") - 1.5).abs() < 1e-4);
+        assert_eq!(history.frecency("never"), 0.0);
+    }
+
+    #[test]
+    fn test_query_choices_share_prefix() {
+        let history = History::new(
+            vec![
+                launch(0, "firefox", "fi"),
+                launch(0, "firefox", "Fire"),
+                launch(0, "files", "fil"),
+                launch(0, "gimp", "g"),
+                launch(0, "calc", ""),
+            ],
+            0,
+        );
+
+        let choices = history.query_choices("fir");
+        assert_eq!(choices.get("firefox"), Some(&2));
+        assert_eq!(choices.get("files"), None);
+        assert_eq!(choices.get("gimp"), None);
+        assert_eq!(choices.get("calc"), None);
+    }
+
+    #[test]
+    fn test_format_roundtrip() {
+        let records = vec![
+            launch(1_700_000_000, "firefox.desktop", "fx"),
+            launch(1_700_000_100, "org.gnome.Nautilus.desktop", "a\tb"),
+        ];
+
+        let parsed = decode(&encode(&records));
+        assert_eq!(parsed[0], records[0]);
+        assert_eq!(parsed[1].query, "a b");
+        assert_eq!(decode("garbage\n"), Vec::new());
+    }
+}
@@ -0,0 +1,224 @@
+use ::image::imageops::FilterType;
+use ::image::{DynamicImage, ImageBuffer, Rgb, RgbaImage};
+use iced::widget::image::Handle;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::APP_NAME;
+use crate::apps::{self, AppInfo};
+use crate::logs;
+
+/// Where an icon's pixels come from
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum IconSource {
+    /// Icon file resolved during discovery
+    File(PathBuf),
+    /// Generated pattern for entries without a usable icon, seeded by their name
+    Fallback(String),
+}
+
+/// Decoded icon identity: the source and the size it is rasterized at
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IconKey {
+    pub source: IconSource,
+    pub size: u32,
+}
+
+impl IconKey {
+    /// Key for an entry's icon, or for its fallback when there is no icon file
+    pub fn new(icon_path: Option<&Path>, name: &str, size: u32) -> Self {
+        let source = match icon_path {
+            Some(path) => IconSource::File(path.to_path_buf()),
+            None => IconSource::Fallback(name.to_string()),
+        };
+        Self { source, size }
+    }
+}
+
+/// Icons decoded off the UI thread, so rendering a frame never touches the disk
+pub struct IconCache {
+    /// Decoded icons; `None` when the file could not be decoded
+    loaded: HashMap<IconKey, Option<Handle>>,
+    /// Icons currently decoding in a background task
+    pending: HashSet<IconKey>,
+    /// Shown until an icon is ready. A single handle, so the renderer uploads it once.
+    placeholder: Handle,
+}
+
+impl Default for IconCache {
+    fn default() -> Self {
+        Self {
+            loaded: HashMap::new(),
+            pending: HashSet::new(),
+            placeholder: Handle::from_rgba(1, 1, vec![255, 255, 255, 24]),
+        }
+    }
+}
+
+impl IconCache {
+    /// Key to display for an entry: its icon file unless that failed to decode,
+    /// in which case the generated fallback for its name
+    pub fn key(&self, icon_path: Option<&Path>, name: &str, size: u32) -> IconKey {
+        let key = IconKey::new(icon_path, name, size);
+        if matches!(self.loaded.get(&key), Some(None)) {
+            IconKey::new(None, name, size)
+        } else {
+            key
+        }
+    }
+
+    /// Decoded icon, or the placeholder while it is still loading
+    pub fn handle(&self, key: &IconKey) -> Handle {
+        match self.loaded.get(key) {
+            Some(Some(handle)) => handle.clone(),
+            _ => self.placeholder.clone(),
+        }
+    }
+
+    /// Mark `key` as loading. Returns false if it is already loaded or loading.
+    pub fn begin_load(&mut self, key: &IconKey) -> bool {
+        !self.loaded.contains_key(key) && self.pending.insert(key.clone())
+    }
+
+    pub fn insert(&mut self, key: IconKey, handle: Option<Handle>) {
+        self.pending.remove(&key);
+        self.loaded.insert(key, handle);
+    }
+}
+
+/// Decode an icon at its target size; meant to run on a background task.
+/// With `persist`, rasterized file icons are kept as PNGs in the cache directory,
+/// keyed by path, size and modification time, so SVGs are rendered only once.
+pub fn load(key: &IconKey, persist: bool) -> Option<Handle> {
+    let image = match &key.source {
+        IconSource::File(path) => {
+            let disk_path = persist.then(|| disk_cache_path(path, key.size)).flatten();
+
+            match disk_path.as_deref().and_then(|p| ::image::open(p).ok()) {
+                Some(cached) => cached.to_rgba8(),
+                None => {
+                    let image = rasterize(path, key.size)?;
+                    if let Some(disk_path) = &disk_path {
+                        save_to_disk(&image, disk_path);
+                    }
+                    image
+                }
+            }
+        }
+        IconSource::Fallback(name) => generate_fallback_icon(name, key.size),
+    };
+
+    let (width, height) = image.dimensions();
+    Some(Handle::from_rgba(width, height, image.into_raw()))
+}
+
+/// Read an icon file through the platform extractor and scale it to `size`
+fn rasterize(path: &Path, size: u32) -> Option<RgbaImage> {
+    let app = AppInfo {
+        icon_path: Some(path.to_path_buf()),
+        ..Default::default()
+    };
+    let bytes = apps::extract_icon(&app).ok()??;
+    let image = ::image::load_from_memory(&bytes).ok()?;
+
+    if image.width() > size || image.height() > size {
+        Some(image.resize(size, size, FilterType::Lanczos3).to_rgba8())
+    } else {
+        Some(image.to_rgba8())
+    }
+}
+
+/// Folder of icons rasterized by earlier launches
+pub fn disk_cache_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join(APP_NAME).join("icons"))
+}
+
+/// Cached PNG for the icon file at `path` as currently modified, at `size`
+fn disk_cache_path(path: &Path, size: u32) -> Option<PathBuf> {
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    let since_epoch = modified.duration_since(SystemTime::UNIX_EPOCH).ok()?;
+
+    let hash = fnv1a(&[
+        path.as_os_str().as_encoded_bytes(),
+        &size.to_le_bytes(),
+        &since_epoch.as_nanos().to_le_bytes(),
+    ]);
+    disk_cache_dir().map(|dir| dir.join(format!("{:016x}.png", hash)))
+}
+
+/// 64-bit FNV-1a hash of `parts` in order. Unlike `DefaultHasher`, its output
+/// never changes between Rust releases, so cached files keep their names.
+fn fnv1a(parts: &[&[u8]]) -> u64 {
+    parts
+        .iter()
+        .flat_map(|part| part.iter())
+        .fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+        })
+}
+
+fn save_to_disk(image: &RgbaImage, disk_path: &Path) {
+    let result = disk_path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .map_err(|e| e.to_string())
+        .and_then(|_| image.save(disk_path).map_err(|e| e.to_string()));
+
+    if let Err(e) = result {
+        logs::log_error(&format!(
+            "Failed to cache icon {}: {}",
+            disk_path.display(),
+            e
+        ));
+    }
+}
+
+/// Generate a deterministic fallback icon for apps without icons
+fn generate_fallback_icon(app_name: &str, size: u32) -> RgbaImage {
+    // Create a deterministic but random-looking icon based on app name
+    let base_seed = app_name.chars().map(|c| c as u64).sum::<u64>();
+    let mut img = ImageBuffer::new(64, 64);
+
+    // Generate a simple pixelated pattern
+    for y in 0..64 {
+        for x in 0..64 {
+            // Create blocks of 8x8 pixels for pixelated effect
+            let block_x = x / 8;
+            let block_y = y / 8;
+            let block_seed = block_x * 8 + block_y;
+
+            let mut block_rng = ChaCha8Rng::seed_from_u64(base_seed + block_seed as u64);
+
+            let intensity = if block_rng.r#gen::<f32>() > 0.5 {
+                200u8
+            } else {
+                50u8
+            };
+            let color = [intensity, intensity, intensity];
+
+            img.put_pixel(x, y, Rgb(color));
+        }
+    }
+
+    DynamicImage::ImageRgb8(img)
+        .resize(size, size, FilterType::Nearest)
+        .to_rgba8()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fnv1a() {
+        // Reference values of the algorithm, which cached file names rely on
+        assert_eq!(fnv1a(&[]), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(&[b"a"]), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(fnv1a(&[b"foo", b"bar"]), fnv1a(&[b"foobar"]));
+        assert_eq!(fnv1a(&[b"foobar"]), 0x8594_4171_f739_67e8);
+    }
+}
@@ -1,8 +1,14 @@
+use std::fs;
 use std::io::{Read, Write};
 use std::net::TcpStream;
 use std::path::PathBuf;
 
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
 use crate::APP_NAME;
+use crate::apps::AppInfo;
+use crate::files::FileMatch;
 
 // Macro to generate daemon address constants with validation
 #[macro_export]
@@ -19,6 +25,12 @@ macro_rules! daemon_addr {
 // Generate the daemon address constants using port 37845
 daemon_addr!(37845);
 
+/// Length of the secret every request starts with
+pub const TOKEN_LEN: usize = 32;
+
+/// Longest request payload the daemon reads; a file query needs far less
+const MAX_REQUEST_LEN: usize = 4096;
+
 // Command protocol - single byte commands
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -26,6 +38,9 @@ pub enum Command {
     Stop = 0x01,
     Show = 0x02,
     Status = 0x03,
+    Catalog = 0x04,
+    /// Followed by the query and a result limit as a payload
+    Files = 0x05,
 }
 
 impl Command {
@@ -34,6 +49,8 @@ impl Command {
             0x01 => Some(Command::Stop),
             0x02 => Some(Command::Show),
             0x03 => Some(Command::Status),
+            0x04 => Some(Command::Catalog),
+            0x05 => Some(Command::Files),
             _ => None,
         }
     }
@@ -50,6 +67,8 @@ pub enum ResponseType {
     Ok = 0x80,
     Status = 0x81,
     Error = 0x82,
+    Catalog = 0x83,
+    Files = 0x84,
 }
 
 // Response enum
@@ -61,6 +80,8 @@ pub enum Response {
         daemon_running: bool,
         ui_visible: bool,
     },
+    Catalog(Vec<AppInfo>),
+    Files(Vec<FileMatch>),
 }
 
 // Standard response messages
@@ -80,10 +101,30 @@ pub mod messages {
 
 // Send command using byte protocol
 pub fn send_command(cmd: Command) -> Result<Response, std::io::Error> {
+    send_request(cmd, &[])
+}
+
+/// Ask the daemon for the files best matching `query`
+pub fn send_file_query(query: &str, limit: usize) -> Result<Response, std::io::Error> {
+    let mut payload = Vec::new();
+    write_payload(&mut payload, &(query, limit))?;
+    send_request(Command::Files, &payload)
+}
+
+/// Read the query and limit following a `Command::Files` byte
+pub fn read_file_query(stream: &mut TcpStream) -> std::io::Result<(String, usize)> {
+    read_payload(stream, MAX_REQUEST_LEN)
+}
+
+fn send_request(cmd: Command, payload: &[u8]) -> Result<Response, std::io::Error> {
+    let token = fs::read(token_file_path())?;
     let mut stream = TcpStream::connect(DAEMON_ADDR)?;
 
-    // Send single byte command
+    // Send the daemon's token and a single byte command, followed by its
+    // arguments if it has any
+    stream.write_all(&token)?;
     stream.write_all(&[cmd.to_byte()])?;
+    stream.write_all(payload)?;
     stream.flush()?;
 
     // Read response type byte
@@ -115,6 +156,12 @@ pub fn send_command(cmd: Command) -> Result<Response, std::io::Error> {
                 ui_visible: flags[0] & 0x02 != 0,
             })
         }
+        x if x == ResponseType::Catalog as u8 => {
+            Ok(Response::Catalog(read_payload(&mut stream, usize::MAX)?))
+        }
+        x if x == ResponseType::Files as u8 => {
+            Ok(Response::Files(read_payload(&mut stream, usize::MAX)?))
+        }
         x if x == ResponseType::Error as u8 => {
             // Error response
             // Read message length (2 bytes)
@@ -166,13 +213,161 @@ pub fn send_response(stream: &mut TcpStream, response: &Response) -> std::io::Re
             stream.write_all(&len)?;
             stream.write_all(msg.as_bytes())?;
         }
+        Response::Catalog(apps) => {
+            stream.write_all(&[ResponseType::Catalog as u8])?;
+            write_payload(stream, apps)?;
+        }
+        Response::Files(files) => {
+            stream.write_all(&[ResponseType::Files as u8])?;
+            write_payload(stream, files)?;
+        }
     }
     stream.flush()
 }
 
+/// Write `value` as JSON, preceded by its length as a u32
+fn write_payload<T: Serialize>(writer: &mut impl Write, value: &T) -> std::io::Result<()> {
+    let payload = serde_json::to_vec(value)?;
+    writer.write_all(&(payload.len() as u32).to_be_bytes())?;
+    writer.write_all(&payload)
+}
+
+/// Read a value written by `write_payload`, refusing one longer than
+/// `max_len` bytes before anything is allocated for it
+fn read_payload<T: DeserializeOwned>(reader: &mut impl Read, max_len: usize) -> std::io::Result<T> {
+    let mut len_buf = [0u8; 4];
+    reader.read_exact(&mut len_buf)?;
+    let len = u32::from_be_bytes(len_buf) as usize;
+    if len > max_len {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("payload of {} bytes exceeds {}", len, max_len),
+        ));
+    }
+
+    let mut payload = vec![0u8; len];
+    reader.read_exact(&mut payload)?;
+    Ok(serde_json::from_slice(&payload)?)
+}
+
 pub fn pid_file_path() -> PathBuf {
+    runtime_dir().join(format!("{}.pid", APP_NAME))
+}
+
+/// File holding the token clients must send, readable only by the user who
+/// started the daemon, so other users on the machine can't talk to it
+pub fn token_file_path() -> PathBuf {
+    runtime_dir().join(format!("{}.token", APP_NAME))
+}
+
+fn runtime_dir() -> PathBuf {
     dirs::runtime_dir()
         .or_else(dirs::cache_dir)
         .unwrap_or_else(|| PathBuf::from("/tmp"))
-        .join(format!("{}.pid", APP_NAME))
+}
+
+/// Generate a new random token and write it to `token_file_path`
+pub fn create_token() -> std::io::Result<Vec<u8>> {
+    use rand::Rng;
+    use rand::distributions::Alphanumeric;
+
+    let token: Vec<u8> = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(TOKEN_LEN)
+        .collect();
+
+    let path = token_file_path();
+    let _ = fs::remove_file(&path);
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(&path)?.write_all(&token)?;
+
+    Ok(token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apps::AppAction;
+
+    #[test]
+    fn test_catalog_round_trip() {
+        let apps = vec![
+            AppInfo {
+                id: "firefox.desktop".to_string(),
+                name: "Firefox".to_string(),
+                generic_name: Some("Web Browser".to_string()),
+                keywords: vec!["Internet".to_string(), "WWW".to_string()],
+                exe_path: PathBuf::from("/usr/bin/firefox"),
+                icon_path: Some(PathBuf::from("/usr/share/icons/firefox.svg")),
+                actions: vec![AppAction {
+                    name: "New Private Window".to_string(),
+                    exe_path: PathBuf::from("/usr/bin/firefox"),
+                    args: vec!["--private-window".to_string()],
+                    icon_path: None,
+                }],
+                ..Default::default()
+            },
+            AppInfo {
+                id: "htop.desktop".to_string(),
+                name: "Htop".to_string(),
+                exe_path: PathBuf::from("/usr/bin/htop"),
+                terminal: true,
+                ..Default::default()
+            },
+        ];
+
+        let mut encoded = Vec::new();
+        write_payload(&mut encoded, &apps).unwrap();
+        let decoded: Vec<AppInfo> =
+            read_payload(&mut encoded.as_slice(), usize::MAX).expect("catalog should decode");
+
+        assert_eq!(decoded, apps);
+        assert!(read_payload::<Vec<AppInfo>>(&mut &encoded[..20], usize::MAX).is_err());
+    }
+
+    #[test]
+    fn test_files_round_trip() {
+        let files = vec![
+            FileMatch {
+                path: PathBuf::from("/home/user/projects/launchdock"),
+                is_dir: true,
+                score: 1361.5,
+                name_matches: vec![0, 1, 2],
+            },
+            FileMatch {
+                path: PathBuf::from("/home/user/Documents/Größe.pdf"),
+                is_dir: false,
+                score: 0.0,
+                name_matches: Vec::new(),
+            },
+        ];
+
+        let mut encoded = Vec::new();
+        write_payload(&mut encoded, &files).unwrap();
+        let decoded: Vec<FileMatch> =
+            read_payload(&mut encoded.as_slice(), usize::MAX).expect("files should decode");
+
+        assert_eq!(decoded, files);
+        assert!(read_payload::<Vec<FileMatch>>(&mut &encoded[..12], usize::MAX).is_err());
+    }
+
+    #[test]
+    fn test_request_too_long() {
+        let mut encoded = Vec::new();
+        write_payload(&mut encoded, &("x".repeat(MAX_REQUEST_LEN), 7)).unwrap();
+        let error =
+            read_payload::<(String, usize)>(&mut encoded.as_slice(), MAX_REQUEST_LEN).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+
+        // A huge length is refused without waiting for the payload
+        let error =
+            read_payload::<(String, usize)>(&mut &[0xff; 4][..], MAX_REQUEST_LEN).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
 }
@@ -1,10 +1,22 @@
 use std::env;
 
 mod apps;
+#[cfg(target_os = "linux")]
+mod cache;
+mod config;
 mod daemon;
+mod favorites;
+mod files;
+mod history;
+mod icons;
 mod ipc;
 mod logs;
+mod providers;
+mod search;
+mod terminal;
 mod ui;
+#[cfg(target_os = "linux")]
+mod watcher;
 
 const APP_NAME: &str = "launchdock";
 const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -17,6 +29,13 @@ fn print_help() {
     println!("  stop          Stop the daemon");
     println!("  show          Show the UI window");
     println!("  status        Display daemon and UI status");
+    println!("  list          List discovered applications");
+    println!("  list --hidden Show excluded desktop entries (--reason explains why)");
+    println!("  cache stats   Show discovery cache statistics (Linux)");
+    println!("  cache clear   Delete the discovery cache and cached icons");
+    println!("  cache rebuild Rescan all desktop entries into a fresh cache");
+    println!("  history       Show launched applications, most frecent first");
+    println!("  history clear Forget all recorded launches");
     println!("  version       Show version information");
     println!("  logs          Show recent log entries (default: 50 lines)");
     println!("  logs <n>      Show last n log entries");
@@ -42,10 +61,14 @@ fn main() {
     }
 
     if args.len() == 2 && args[1] == "--ui-mode" {
-        let result = apps::discover_applications().and_then(|apps| {
-            logs::log_info(&format!("Found {} applications", apps.len()));
-            ui::run_ui(apps)
+        // Use the daemon's catalog; scan directly only if it is unreachable
+        let catalog = daemon::catalog().or_else(|e| {
+            logs::log_error(&format!("Failed to load catalog from daemon: {}", e));
+            apps::discover_applications().inspect(|apps| {
+                logs::log_info(&format!("Found {} applications", apps.len()));
+            })
         });
+        let result = catalog.and_then(ui::run_ui);
 
         if let Err(e) = result {
             logs::log_error(&format!("Application error: {}", e));
@@ -64,6 +87,31 @@ fn main() {
             "stop" => daemon::stop(),
             "show" => daemon::show(),
             "status" => daemon::status(),
+            "list" => {
+                let flags = &args[2..];
+                match flags
+                    .iter()
+                    .find(|flag| !matches!(flag.as_str(), "--hidden" | "--reason"))
+                {
+                    Some(flag) => Err(format!("Invalid list argument: {}", flag)),
+                    None => apps::list(
+                        flags.iter().any(|flag| flag == "--hidden"),
+                        flags.iter().any(|flag| flag == "--reason"),
+                    ),
+                }
+            }
+            #[cfg(target_os = "linux")]
+            "cache" => match args.get(2).map(String::as_str) {
+                Some("clear") => cache::clear(),
+                Some("rebuild") => cache::rebuild(),
+                Some("stats") | None => cache::stats(),
+                Some(arg) => Err(format!("Invalid cache argument: {}", arg)),
+            },
+            "history" => match args.get(2).map(String::as_str) {
+                Some("clear") => history::clear(),
+                None => history::show(),
+                Some(arg) => Err(format!("Invalid history argument: {}", arg)),
+            },
             "version" => {
                 print_version();
                 Ok(())
@@ -0,0 +1,437 @@
+use crate::config::IconConfig;
+use std::cell::OnceCell;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::{env, fs};
+
+/// Icon file extensions, in the order the icon theme spec prefers them
+const EXTENSIONS: [&str; 3] = ["png", "svg", "xpm"];
+
+/// Legacy directory of unthemed icons, searched last
+pub const PIXMAPS_DIR: &str = "/usr/share/pixmaps";
+
+/// Icon lookup following the freedesktop icon theme specification: the user's
+/// theme, its `Inherits` chain and finally hicolor, picking the directory whose
+/// size best fits the size the launcher draws icons at
+pub struct IconTheme {
+    /// Name of the active theme, e.g. "Papirus-Dark"
+    pub name: String,
+    /// Target icon size in logical pixels
+    pub size: u32,
+    /// Target scale factor, e.g. 2 on HiDPI screens
+    pub scale: u32,
+    base_dirs: Vec<PathBuf>,
+    /// Themes in lookup order; indexed on first lookup, so runs that only hit
+    /// the discovery cache never read a theme directory
+    themes: OnceCell<Vec<IndexedTheme>>,
+}
+
+impl IconTheme {
+    pub fn new(config: &IconConfig, size: u32, desktops: &[String]) -> Self {
+        Self {
+            name: config
+                .theme
+                .clone()
+                .or_else(|| desktop_icon_theme(desktops))
+                .unwrap_or_else(|| "hicolor".to_string()),
+            size,
+            scale: config.scale.or_else(env_scale).unwrap_or(1).max(1),
+            base_dirs: base_directories(),
+            themes: OnceCell::new(),
+        }
+    }
+
+    /// Resolve an `Icon` value: absolute paths are used as is, names are looked up
+    /// in the theme chain and then directly in the base directories and pixmaps
+    pub fn find(&self, icon_name: &str) -> Option<PathBuf> {
+        if icon_name.starts_with('/') {
+            let path = PathBuf::from(icon_name);
+            return path.exists().then_some(path);
+        }
+
+        // Names should not carry an extension, but plenty of entries add one anyway
+        let name = EXTENSIONS
+            .iter()
+            .find_map(|ext| icon_name.strip_suffix(&format!(".{}", ext)))
+            .unwrap_or(icon_name);
+
+        self.themes
+            .get_or_init(|| self.index_themes())
+            .iter()
+            .find_map(|theme| theme.lookup(name, self.size, self.scale))
+            .or_else(|| self.find_fallback(name))
+    }
+
+    /// Unthemed icons placed directly in a base directory or in pixmaps
+    fn find_fallback(&self, name: &str) -> Option<PathBuf> {
+        self.base_dirs
+            .iter()
+            .map(PathBuf::as_path)
+            .chain([Path::new(PIXMAPS_DIR)])
+            .flat_map(|dir| EXTENSIONS.map(|ext| dir.join(format!("{}.{}", name, ext))))
+            .find(|path| path.exists())
+    }
+
+    /// Load the active theme and every theme it inherits from, depth-first as the
+    /// spec's recursive lookup visits them, followed by hicolor
+    fn index_themes(&self) -> Vec<IndexedTheme> {
+        let mut themes = Vec::new();
+        let mut visited = HashSet::new();
+        // A stack, so the active theme is searched first and hicolor last
+        let mut pending = vec!["hicolor".to_string(), self.name.clone()];
+
+        while let Some(name) = pending.pop() {
+            if !visited.insert(name.clone()) {
+                continue;
+            }
+            let Some((theme, parents)) = IndexedTheme::load(&name, &self.base_dirs) else {
+                continue;
+            };
+            themes.push(theme);
+
+            // Reversed so the first parent is visited next
+            pending.extend(parents.into_iter().rev());
+        }
+
+        themes
+    }
+}
+
+/// Directories searched for themes, in spec order: `~/.icons`, then the `icons`
+/// directory of each XDG data directory, user first
+pub fn base_directories() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+
+    if let Some(home) = dirs::home_dir() {
+        dirs.push(home.join(".icons"));
+    }
+    if let Some(data_home) = dirs::data_dir() {
+        dirs.push(data_home.join("icons"));
+    }
+
+    // XDG_DATA_DIRS defaults to /usr/local/share:/usr/share
+    env::var("XDG_DATA_DIRS")
+        .unwrap_or_else(|_| "/usr/local/share:/usr/share".to_string())
+        .split(':')
+        .filter(|dir| !dir.is_empty())
+        .map(|dir| Path::new(dir).join("icons"))
+        .for_each(|dir| {
+            if !dirs.contains(&dir) {
+                dirs.push(dir);
+            }
+        });
+
+    dirs
+}
+
+/// How a theme directory's icons may be scaled, from its `Type` key
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SizeType {
+    Fixed,
+    Scalable,
+    Threshold,
+}
+
+/// One directory listed in a theme's `index.theme`, e.g. `48x48/apps`
+#[derive(Debug, Clone)]
+struct ThemeDir {
+    path: String,
+    size: u32,
+    scale: u32,
+    min_size: u32,
+    max_size: u32,
+    threshold: u32,
+    size_type: SizeType,
+}
+
+impl ThemeDir {
+    fn parse(path: &str, keys: &HashMap<String, String>) -> Option<Self> {
+        let number = |key: &str| keys.get(key).and_then(|value| value.trim().parse().ok());
+        let size = number("Size")?;
+
+        Some(Self {
+            path: path.to_string(),
+            size,
+            scale: number("Scale").unwrap_or(1),
+            min_size: number("MinSize").unwrap_or(size),
+            max_size: number("MaxSize").unwrap_or(size),
+            threshold: number("Threshold").unwrap_or(2),
+            size_type: match keys.get("Type").map(|value| value.trim()) {
+                Some("Fixed") => SizeType::Fixed,
+                Some("Scalable") => SizeType::Scalable,
+                _ => SizeType::Threshold,
+            },
+        })
+    }
+
+    /// `DirectoryMatchesSize` from the spec
+    fn matches(&self, size: u32, scale: u32) -> bool {
+        if self.scale != scale {
+            return false;
+        }
+
+        match self.size_type {
+            SizeType::Fixed => self.size == size,
+            SizeType::Scalable => (self.min_size..=self.max_size).contains(&size),
+            SizeType::Threshold => (self.size.saturating_sub(self.threshold)
+                ..=self.size + self.threshold)
+                .contains(&size),
+        }
+    }
+
+    /// `DirectorySizeDistance` from the spec, in physical pixels. Threshold
+    /// directories measure from the edges of their threshold range; the spec's
+    /// pseudocode uses MinSize/MaxSize there, which only Scalable directories have.
+    fn distance(&self, size: u32, scale: u32) -> u32 {
+        let target = size * scale;
+        let (min, max) = match self.size_type {
+            SizeType::Fixed => (self.size, self.size),
+            SizeType::Scalable => (self.min_size, self.max_size),
+            SizeType::Threshold => (
+                self.size.saturating_sub(self.threshold),
+                self.size + self.threshold,
+            ),
+        };
+        let (min, max) = (min * self.scale, max * self.scale);
+
+        if target < min {
+            min - target
+        } else {
+            target.saturating_sub(max)
+        }
+    }
+}
+
+/// Theme whose directories have been listed once, so lookups don't touch the disk
+struct IndexedTheme {
+    dirs: Vec<ThemeDir>,
+    /// Icon files by name, ordered by theme directory, then base directory, then
+    /// preferred extension; the number is an index into `dirs`
+    icons: HashMap<String, Vec<(usize, PathBuf)>>,
+}
+
+impl IndexedTheme {
+    /// Read the theme's `index.theme` from the first base directory that has one
+    /// and list its icon directories across all base directories. Also returns
+    /// the themes it inherits from.
+    fn load(name: &str, base_dirs: &[PathBuf]) -> Option<(Self, Vec<String>)> {
+        let content = base_dirs
+            .iter()
+            .find_map(|dir| fs::read_to_string(dir.join(name).join("index.theme")).ok())?;
+        let sections = parse_index(&content);
+        let header = sections.get("Icon Theme")?;
+
+        let list = |key: &str| -> Vec<String> {
+            header
+                .get(key)
+                .map(|value| {
+                    value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|item| !item.is_empty())
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        let dirs: Vec<ThemeDir> = list("Directories")
+            .into_iter()
+            .chain(list("ScaledDirectories"))
+            .filter_map(|path| ThemeDir::parse(&path, sections.get(&path)?))
+            .collect();
+
+        let mut candidates: HashMap<String, Vec<(usize, usize, usize, PathBuf)>> = HashMap::new();
+        for (dir_index, dir) in dirs.iter().enumerate() {
+            for (base_index, base_dir) in base_dirs.iter().enumerate() {
+                let Ok(entries) = fs::read_dir(base_dir.join(name).join(&dir.path)) else {
+                    continue;
+                };
+
+                for path in entries.flatten().map(|entry| entry.path()) {
+                    let (Some(stem), Some(ext)) = (
+                        path.file_stem().and_then(|stem| stem.to_str()),
+                        path.extension().and_then(|ext| ext.to_str()),
+                    ) else {
+                        continue;
+                    };
+                    let Some(ext_rank) = EXTENSIONS.iter().position(|known| *known == ext) else {
+                        continue;
+                    };
+
+                    candidates
+                        .entry(stem.to_string())
+                        .or_default()
+                        .push((dir_index, base_index, ext_rank, path));
+                }
+            }
+        }
+
+        let icons = candidates
+            .into_iter()
+            .map(|(icon_name, mut files)| {
+                files.sort_by_key(|(dir, base, ext, _)| (*dir, *base, *ext));
+                let files = files.into_iter().map(|(dir, _, _, path)| (dir, path));
+                (icon_name, files.collect())
+            })
+            .collect();
+
+        Some((Self { dirs, icons }, list("Inherits")))
+    }
+
+    /// `LookupIcon` from the spec: the first file in a directory matching the size
+    /// exactly, otherwise the file in the directory with the closest size
+    fn lookup(&self, name: &str, size: u32, scale: u32) -> Option<PathBuf> {
+        let files = self.icons.get(name)?;
+
+        files
+            .iter()
+            .find(|(dir, _)| self.dirs[*dir].matches(size, scale))
+            .or_else(|| {
+                files
+                    .iter()
+                    .min_by_key(|(dir, _)| self.dirs[*dir].distance(size, scale))
+            })
+            .map(|(_, path)| path.clone())
+    }
+}
+
+/// Split an `index.theme` (or any ini-style file) into sections of key/value pairs
+fn parse_index(content: &str) -> HashMap<String, HashMap<String, String>> {
+    let mut sections: HashMap<String, HashMap<String, String>> = HashMap::new();
+    let mut current = String::new();
+
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            current = section.to_string();
+        } else if let Some((key, value)) = line.split_once('=') {
+            sections
+                .entry(current.clone())
+                .or_default()
+                .insert(key.trim().to_string(), value.trim().to_string());
+        }
+    }
+
+    sections
+}
+
+/// Icon theme chosen in the desktop's own settings. KDE keeps it in `kdeglobals`;
+/// GTK desktops in GSettings, mirrored into the GTK `settings.ini` files.
+fn desktop_icon_theme(desktops: &[String]) -> Option<String> {
+    let is_kde = desktops
+        .iter()
+        .any(|desktop| desktop.eq_ignore_ascii_case("KDE"));
+    let config_dir = dirs::config_dir();
+    let setting = |file: &str, section: &str, key: &str| -> Option<String> {
+        let content = fs::read_to_string(config_dir.as_ref()?.join(file)).ok()?;
+        parse_index(&content)
+            .get(section)?
+            .get(key)
+            .map(|value| value.trim_matches('"').to_string())
+            .filter(|value| !value.is_empty())
+    };
+
+    let kde_theme = || setting("kdeglobals", "Icons", "Theme");
+    let gtk_theme = || {
+        gsettings_icon_theme()
+            .or_else(|| setting("gtk-4.0/settings.ini", "Settings", "gtk-icon-theme-name"))
+            .or_else(|| setting("gtk-3.0/settings.ini", "Settings", "gtk-icon-theme-name"))
+    };
+
+    if is_kde {
+        kde_theme().or_else(|| Some("breeze".to_string()))
+    } else {
+        gtk_theme().or_else(kde_theme)
+    }
+}
+
+fn gsettings_icon_theme() -> Option<String> {
+    let output = Command::new("gsettings")
+        .args(["get", "org.gnome.desktop.interface", "icon-theme"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+
+    String::from_utf8(output.stdout)
+        .ok()
+        .map(|value| value.trim().trim_matches('\'').to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// Integer scale factor set for GTK or Qt applications
+fn env_scale() -> Option<u32> {
+    ["GDK_SCALE", "QT_SCALE_FACTOR"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find_map(|value| value.trim().parse::<f32>().ok())
+        .map(|scale| scale.round() as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dir(keys: &[(&str, &str)]) -> ThemeDir {
+        let keys = keys
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        ThemeDir::parse("test", &keys).unwrap()
+    }
+
+    #[test]
+    fn test_directory_matches_size() {
+        let fixed = dir(&[("Size", "48"), ("Type", "Fixed")]);
+        assert!(fixed.matches(48, 1));
+        assert!(!fixed.matches(47, 1));
+        assert!(!fixed.matches(48, 2));
+
+        let scalable = dir(&[
+            ("Size", "64"),
+            ("MinSize", "16"),
+            ("MaxSize", "256"),
+            ("Type", "Scalable"),
+        ]);
+        assert!(scalable.matches(48, 1));
+        assert!(!scalable.matches(512, 1));
+
+        // Threshold is the default type, with a default threshold of 2
+        let threshold = dir(&[("Size", "48")]);
+        assert!(threshold.matches(46, 1));
+        assert!(threshold.matches(50, 1));
+        assert!(!threshold.matches(51, 1));
+    }
+
+    #[test]
+    fn test_directory_size_distance() {
+        let fixed = dir(&[("Size", "32"), ("Type", "Fixed")]);
+        assert_eq!(fixed.distance(48, 1), 16);
+
+        let hidpi = dir(&[("Size", "24"), ("Scale", "2"), ("Type", "Fixed")]);
+        assert_eq!(hidpi.distance(24, 2), 0);
+        assert_eq!(hidpi.distance(48, 1), 0);
+
+        let scalable = dir(&[
+            ("Size", "64"),
+            ("MinSize", "64"),
+            ("MaxSize", "256"),
+            ("Type", "Scalable"),
+        ]);
+        assert_eq!(scalable.distance(48, 1), 16);
+        assert_eq!(scalable.distance(128, 1), 0);
+    }
+
+    #[test]
+    fn test_parse_index() {
+        let sections = parse_index(
+            "[Icon Theme]\nName=Test\nInherits=Adwaita,hicolor\n# comment\n\n[48x48/apps]\nSize=48\n",
+        );
+        assert_eq!(sections["Icon Theme"]["Inherits"], "Adwaita,hicolor");
+        assert_eq!(sections["48x48/apps"]["Size"], "48");
+    }
+}
@@ -1,27 +1,29 @@
-use crate::apps::AppInfo;
+use crate::apps::{
+    self, AppAction, AppInfo, CatalogChange, ExcludedEntry, SystemCommand, find_executable,
+};
+use crate::cache::{self, CachedEntry, DiscoveryCache};
+use crate::config::{Config, IconConfig};
+use crate::logs;
 use freedesktop_desktop_entry::{DesktopEntry, Iter, default_paths};
 use rs_apply::Apply;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
-use std::path::PathBuf;
+use std::fmt;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::{env, fs};
 
-pub fn discover_applications() -> Result<Vec<AppInfo>, Box<dyn Error>> {
-    let mut unique_apps: HashMap<PathBuf, AppInfo> = HashMap::new();
+#[path = "icon_theme.rs"]
+mod icon_theme;
 
-    for result in discover_desktop_entries().chain(special_commands()) {
-        match result {
-            Ok(app) => {
-                // Only insert if we haven't seen this executable path before
-                // This preserves the first occurrence (highest priority)
-                unique_apps.entry(app.exe_path.clone()).or_insert(app);
-            }
-            Err(_) => continue, // Skip entries with errors
-        }
-    }
+use icon_theme::IconTheme;
 
-    Ok(unique_apps.into_values().collect())
+pub fn discover_applications() -> Result<Vec<AppInfo>, Box<dyn Error>> {
+    Ok(scan_desktop_entries(&ScanSettings::current()).apps)
+}
+
+pub fn discover_excluded_entries() -> Vec<ExcludedEntry> {
+    scan_desktop_entries(&ScanSettings::current()).excluded
 }
 
 pub fn extract_icon(app: &AppInfo) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
@@ -43,16 +45,17 @@ pub fn extract_icon(app: &AppInfo) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
                     let width = size.width() as u32;
                     let height = size.height() as u32;
 
-                    // Target 48x48 or maintain aspect ratio
-                    let (target_w, target_h) = if width > 48 || height > 48 {
-                        let scale = 48.0 / width.max(height) as f32;
-                        (
-                            (width as f32 * scale) as u32,
-                            (height as f32 * scale) as u32,
-                        )
-                    } else {
-                        (width, height)
-                    };
+                    // Target the launcher's icon size or maintain aspect ratio
+                    let (target_w, target_h) =
+                        if width > apps::ICON_SIZE || height > apps::ICON_SIZE {
+                            let scale = apps::ICON_SIZE as f32 / width.max(height) as f32;
+                            (
+                                (width as f32 * scale) as u32,
+                                (height as f32 * scale) as u32,
+                            )
+                        } else {
+                            (width, height)
+                        };
 
                     let mut pixmap = tiny_skia::Pixmap::new(target_w, target_h)
                         .ok_or("Failed to create pixmap")?;
@@ -79,15 +82,11 @@ pub fn extract_icon(app: &AppInfo) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
     }
 }
 
-fn special_commands() -> impl Iterator<Item = Result<AppInfo, Box<dyn Error>>> {
-    let icon_path = [
-        "/usr/share/icons/hicolor/scalable/actions/system-shutdown.svg",
-        "/usr/share/icons/Adwaita/scalable/actions/system-shutdown-symbolic.svg",
-        "/usr/share/pixmaps/system-shutdown.png",
-    ]
-    .iter()
-    .map(PathBuf::from)
-    .find(|path| path.exists());
+pub fn system_commands(config: &IconConfig) -> Vec<SystemCommand> {
+    let icons = IconTheme::new(config, apps::ICON_SIZE, &current_desktops());
+    let icon_path = icons
+        .find("system-shutdown")
+        .or_else(|| icons.find("system-shutdown-symbolic"));
 
     let username = env::var("USER").ok().filter(|s| !s.is_empty()).or_else(|| {
         Command::new("whoami")
@@ -98,178 +97,1016 @@ fn special_commands() -> impl Iterator<Item = Result<AppInfo, Box<dyn Error>>> {
     });
 
     let mut commands = vec![
-        ("Shutdown", "systemctl poweroff".to_string()),
-        ("Restart", "systemctl reboot".to_string()),
-        ("Lock Screen", "loginctl lock-session".to_string()),
+        ("Shutdown", "systemctl", vec!["poweroff".to_string()]),
+        ("Restart", "systemctl", vec!["reboot".to_string()]),
+        ("Lock Screen", "loginctl", vec!["lock-session".to_string()]),
     ];
 
     if let Some(user) = username {
-        commands.push(("Logout", format!("loginctl terminate-user {}", user)));
+        commands.push((
+            "Logout",
+            "loginctl",
+            vec!["terminate-user".to_string(), user],
+        ));
     }
 
-    commands.into_iter().map(move |(name, command)| {
-        Ok(AppInfo {
+    commands
+        .into_iter()
+        .map(|(name, program, args)| SystemCommand {
             name: name.to_string(),
-            exe_path: PathBuf::from(command),
+            program: PathBuf::from(program),
+            args,
             icon_path: icon_path.clone(),
         })
+        .collect()
+}
+
+/// Desktop entry file found in one of the XDG `applications` directories
+struct DesktopFile {
+    /// Desktop-file ID derived from the path relative to its `applications` directory
+    id: String,
+    /// Location of the file on disk
+    path: PathBuf,
+}
+
+/// Directories holding desktop entries, in XDG data directory precedence order
+/// (user first). Flatpak export directories are appended when `XDG_DATA_DIRS`
+/// doesn't already list them, e.g. when launched outside a login session.
+fn application_dirs() -> Vec<PathBuf> {
+    let mut app_dirs = default_paths();
+    let flatpak_dirs = [
+        dirs::data_dir().map(|dir| dir.join("flatpak/exports/share/applications")),
+        Some(PathBuf::from("/var/lib/flatpak/exports/share/applications")),
+    ];
+
+    for dir in flatpak_dirs.into_iter().flatten() {
+        if !app_dirs.contains(&dir) {
+            app_dirs.push(dir);
+        }
+    }
+
+    app_dirs
+}
+
+/// All desktop entry files in `app_dirs`, which are in XDG data directory
+/// precedence order (user first)
+fn desktop_files(app_dirs: &[PathBuf]) -> impl Iterator<Item = DesktopFile> + '_ {
+    app_dirs.iter().flat_map(|dir| {
+        Iter::new(vec![dir.clone()])
+            .filter_map(move |path| desktop_file_id(dir, &path).map(|id| DesktopFile { id, path }))
     })
 }
 
-fn discover_desktop_entries() -> impl Iterator<Item = Result<AppInfo, Box<dyn Error>>> {
-    Iter::new(default_paths()).filter_map(|path| {
-        let content = fs::read_to_string(&path).ok()?;
-
-        if let Ok(entry) = DesktopEntry::decode(&path, &content) {
-            if is_application_entry(&entry) {
-                let name = entry
-                    .name(None)
-                    .map(|cow| cow.to_string())
-                    .unwrap_or_else(|| "Unknown Application".to_string());
-                let exec = entry.exec()?;
-                let icon = entry.icon();
-
-                let exe_path = match resolve_executable(exec) {
-                    Ok(path) => path,
-                    Err(e) => return Some(Err(e)),
-                };
-
-                let icon_path = icon.and_then(resolve_icon_path);
-
-                Some(Ok(AppInfo {
-                    name,
-                    exe_path,
-                    icon_path,
-                }))
-            } else {
-                None
+/// Compute the desktop-file ID as defined by the XDG spec: the path relative to the
+/// `applications` directory with `/` replaced by `-`, e.g. `kde/konsole.desktop`
+/// becomes `kde-konsole.desktop`
+fn desktop_file_id(applications_dir: &Path, path: &Path) -> Option<String> {
+    path.strip_prefix(applications_dir)
+        .ok()?
+        .to_str()
+        .map(|relative| relative.replace('/', "-"))
+}
+
+/// Environment shared by every entry of a scan
+struct ScanSettings {
+    /// Desktop environments from `XDG_CURRENT_DESKTOP`
+    desktops: Vec<String>,
+    /// Locale used for localized keys
+    locale: Option<String>,
+    /// Icon theme lookup at the launcher's icon size
+    icons: IconTheme,
+}
+
+impl ScanSettings {
+    fn current() -> Self {
+        let desktops = current_desktops();
+        let icons = IconTheme::new(&Config::load().icons, apps::ICON_SIZE, &desktops);
+
+        Self {
+            desktops,
+            locale: current_locale(),
+            icons,
+        }
+    }
+}
+
+/// Visible applications and the entries left out of them
+struct DesktopScan {
+    apps: Vec<AppInfo>,
+    excluded: Vec<ExcludedEntry>,
+}
+
+fn scan_desktop_entries(settings: &ScanSettings) -> DesktopScan {
+    let mut cache = DiscoveryCache::load(cache_context(settings));
+    let mut seen = HashSet::new();
+    let mut parsed = 0;
+    let mut owners: HashMap<String, PathBuf> = HashMap::new();
+    let mut scan = DesktopScan {
+        apps: Vec::new(),
+        excluded: Vec::new(),
+    };
+
+    for file in desktop_files(&application_dirs()) {
+        // Only the first file with a given ID counts, even if it is hidden.
+        // Data directories are searched user-first, so user entries override
+        // system ones and can mask them with Hidden=true.
+        let result = match owners.get(&file.id) {
+            Some(owner) => Err(Exclusion::Overridden(owner.clone())),
+            None => {
+                owners.insert(file.id.clone(), file.path.clone());
+                seen.insert(file.path.clone());
+                let (result, reparsed) = load_cached_entry(&file, settings, &mut cache);
+                parsed += reparsed as usize;
+                result
             }
-        } else {
-            None
+        };
+
+        match result {
+            Ok(app) => scan.apps.push(app),
+            Err(exclusion) => scan.excluded.push(ExcludedEntry {
+                id: file.id,
+                path: file.path,
+                reason: exclusion.to_string(),
+            }),
         }
-    })
+    }
+
+    if cache.prune(&seen) || parsed > 0 {
+        logs::log_info(&format!(
+            "Parsed {} changed desktop entries, {} from cache",
+            parsed,
+            seen.len() - parsed
+        ));
+        if let Err(e) = cache.save() {
+            logs::log_error(&format!("Failed to save discovery cache: {}", e));
+        }
+    }
+
+    scan
+}
+
+/// Directories whose contents make up the catalog: every `applications`
+/// directory plus the icon directories icons are resolved from
+pub fn watch_directories() -> Vec<PathBuf> {
+    let mut dirs = application_dirs();
+    dirs.extend(get_icon_base_directories());
+    dirs
 }
 
-fn is_application_entry(entry: &DesktopEntry) -> bool {
-    entry.name(None).is_some() && entry.exec().is_some() && !entry.no_display()
+/// Re-read the desktop entries affected by changes to `paths` and update `apps`
+/// in place, leaving every other entry untouched. The discovery cache is
+/// updated to match, so the next start doesn't parse them again.
+pub fn apply_changes(apps: &mut Vec<AppInfo>, paths: &[PathBuf]) -> Vec<CatalogChange> {
+    let settings = ScanSettings::current();
+    let mut cache = DiscoveryCache::load(cache_context(&settings));
+    let changes = update_catalog(
+        apps,
+        paths,
+        &application_dirs(),
+        &get_icon_base_directories(),
+        &settings,
+        &mut cache,
+    );
+    if let Err(e) = cache.save() {
+        logs::log_error(&format!("Failed to save discovery cache: {}", e));
+    }
+    changes
 }
 
-fn resolve_executable(exec: &str) -> Result<PathBuf, Box<dyn Error>> {
-    exec.split_whitespace()
-        .next()
-        .unwrap_or(exec)
-        .trim_start_matches('"')
-        .trim_end_matches('"')
-        .apply(|command| {
-            if command.starts_with('/') {
-                return Ok(PathBuf::from(command));
+/// `apply_changes` with the given `applications` and icon directories
+fn update_catalog(
+    apps: &mut Vec<AppInfo>,
+    paths: &[PathBuf],
+    app_dirs: &[PathBuf],
+    icon_dirs: &[PathBuf],
+    settings: &ScanSettings,
+    cache: &mut DiscoveryCache,
+) -> Vec<CatalogChange> {
+    // Desktop files map to their ID; directories to the prefix shared by the IDs of
+    // the files inside them (the empty prefix for an `applications` directory itself)
+    let mut ids = HashSet::new();
+    let mut id_prefixes = Vec::new();
+    let mut icon_names = HashSet::new();
+    let mut all_icons = false;
+
+    for path in paths {
+        let extension = path.extension().and_then(|ext| ext.to_str());
+
+        if let Some(id) = app_dirs.iter().find_map(|dir| desktop_file_id(dir, path)) {
+            match extension {
+                Some("desktop") => {
+                    ids.insert(id);
+                }
+                None if id.is_empty() => id_prefixes.push(id),
+                None => id_prefixes.push(format!("{}-", id)),
+                Some(_) => {}
+            }
+        } else if icon_dirs.iter().any(|dir| path.starts_with(dir)) {
+            match (extension, path.file_stem().and_then(|stem| stem.to_str())) {
+                (Some(_), Some(stem)) => {
+                    icon_names.insert(stem.to_string());
+                }
+                _ => all_icons = true,
             }
-            std::env::var("PATH")?
-                .split(':')
-                .map(|dir| PathBuf::from(dir).join(command))
-                .find(|path| path.exists())
-                .unwrap_or_else(|| PathBuf::from(command))
-                .apply(Ok)
+        }
+    }
+
+    let is_affected_id =
+        |id: &str| ids.contains(id) || id_prefixes.iter().any(|prefix| id.starts_with(prefix));
+
+    // An icon change can only matter to entries showing that icon or lacking one
+    let icons_changed = all_icons || !icon_names.is_empty();
+    let is_affected_icon = |app: &AppInfo| {
+        all_icons
+            || app.icon_path.as_ref().is_none_or(|icon_path| {
+                icon_path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .is_some_and(|stem| icon_names.contains(stem))
+            })
+    };
+
+    let mut targets: HashSet<String> = apps
+        .iter()
+        .filter(|app| app.id.ends_with(".desktop"))
+        .filter(|app| is_affected_id(&app.id) || (icons_changed && is_affected_icon(app)))
+        .map(|app| app.id.clone())
+        .collect();
+
+    // The first file with an ID wins, exactly as in a full scan
+    let mut owners: HashMap<String, DesktopFile> = HashMap::new();
+    for file in desktop_files(app_dirs) {
+        if (targets.contains(&file.id) || is_affected_id(&file.id))
+            && !owners.contains_key(&file.id)
+        {
+            owners.insert(file.id.clone(), file);
+        }
+    }
+    targets.extend(owners.keys().cloned());
+
+    let mut targets: Vec<String> = targets.into_iter().collect();
+    targets.sort();
+
+    cache.prune_missing(paths);
+
+    // Entries are parsed again even if cached, as their icons may have been
+    // installed since
+    let mut changes = Vec::new();
+    for id in targets {
+        let loaded = owners
+            .get(&id)
+            .and_then(|file| reload_desktop_entry(file, settings, cache).ok());
+        let position = apps.iter().position(|app| app.id == id);
+
+        match (loaded, position) {
+            (Some(app), Some(index)) if apps[index] != app => {
+                apps[index] = app.clone();
+                changes.push(CatalogChange::Updated(app));
+            }
+            (Some(app), None) => {
+                apps.push(app.clone());
+                changes.push(CatalogChange::Added(app));
+            }
+            (None, Some(index)) => changes.push(CatalogChange::Removed(apps.remove(index))),
+            _ => {}
+        }
+    }
+
+    changes
+}
+
+/// Why a desktop entry is left out of the launcher
+#[derive(Debug)]
+enum Exclusion {
+    Unreadable(String),
+    NotApplication(String),
+    MissingKey(&'static str),
+    Hidden,
+    NoDisplay,
+    OnlyShowIn(String),
+    NotShowIn(String),
+    TryExecMissing(String),
+    InvalidExec(String),
+    Overridden(PathBuf),
+    /// Reason recorded by an earlier scan, reused from the discovery cache
+    Cached(String),
+}
+
+impl fmt::Display for Exclusion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Exclusion::Unreadable(e) => write!(f, "Unreadable: {}", e),
+            Exclusion::NotApplication(kind) => write!(f, "Type={} is not an application", kind),
+            Exclusion::MissingKey(key) => write!(f, "Missing {} key", key),
+            Exclusion::Hidden => write!(f, "Hidden=true"),
+            Exclusion::NoDisplay => write!(f, "NoDisplay=true"),
+            Exclusion::OnlyShowIn(list) => {
+                write!(f, "OnlyShowIn={} excludes {}", list, desktops_label())
+            }
+            Exclusion::NotShowIn(list) => {
+                write!(f, "NotShowIn={} includes {}", list, desktops_label())
+            }
+            Exclusion::TryExecMissing(binary) => write!(f, "TryExec {} is not installed", binary),
+            Exclusion::InvalidExec(e) => write!(f, "Invalid Exec: {}", e),
+            Exclusion::Overridden(owner) => write!(f, "Overridden by {}", owner.display()),
+            Exclusion::Cached(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+/// Everything besides the entry file itself that shapes the parsed result. The
+/// discovery cache is discarded when any of it changes. Installed icons don't
+/// count: each entry's resolved icons are checked on their own when it is loaded.
+fn cache_context(settings: &ScanSettings) -> String {
+    format!(
+        "locale={};desktops={};path={};icons={}@{}x{}",
+        settings.locale.as_deref().unwrap_or_default(),
+        settings.desktops.join(":"),
+        env::var("PATH").unwrap_or_default(),
+        settings.icons.name,
+        settings.icons.size,
+        settings.icons.scale,
+    )
+}
+
+/// Load a desktop entry, reusing the cached outcome when neither the file, its
+/// `TryExec` binary nor its icons changed. Also returns whether the file had to
+/// be parsed.
+fn load_cached_entry(
+    file: &DesktopFile,
+    settings: &ScanSettings,
+    cache: &mut DiscoveryCache,
+) -> (Result<AppInfo, Exclusion>, bool) {
+    let Some(mtime) = cache::mtime(&file.path) else {
+        return (load_desktop_entry(file, settings), true);
+    };
+
+    let cached = cache.get(&file.path, mtime).filter(|cached| {
+        cached
+            .try_exec
+            .as_ref()
+            .is_none_or(|(binary, found)| find_executable(binary).is_some() == *found)
+            && cached.icons_current()
+    });
+    if let Some(cached) = cached {
+        return (cached.result.clone().map_err(Exclusion::Cached), false);
+    }
+
+    (reload_desktop_entry(file, settings, cache), true)
+}
+
+/// Parse a desktop entry, replacing its cached outcome
+fn reload_desktop_entry(
+    file: &DesktopFile,
+    settings: &ScanSettings,
+    cache: &mut DiscoveryCache,
+) -> Result<AppInfo, Exclusion> {
+    let Some(mtime) = cache::mtime(&file.path) else {
+        return load_desktop_entry(file, settings);
+    };
+
+    let (try_exec, result) = read_desktop_entry(file, settings);
+    cache.insert(
+        file.path.clone(),
+        CachedEntry::new(
+            mtime,
+            try_exec,
+            result.as_ref().map_err(ToString::to_string).cloned(),
+        ),
+    );
+
+    result
+}
+
+fn load_desktop_entry(file: &DesktopFile, settings: &ScanSettings) -> Result<AppInfo, Exclusion> {
+    read_desktop_entry(file, settings).1
+}
+
+/// Parse a desktop entry file, also returning its `TryExec` binary and whether it
+/// was found, since the result depends on it
+fn read_desktop_entry(
+    file: &DesktopFile,
+    settings: &ScanSettings,
+) -> (Option<(String, bool)>, Result<AppInfo, Exclusion>) {
+    let content = match fs::read_to_string(&file.path) {
+        Ok(content) => content,
+        Err(e) => return (None, Err(Exclusion::Unreadable(e.to_string()))),
+    };
+    let entry = match DesktopEntry::decode(&file.path, &content) {
+        Ok(entry) => entry,
+        Err(e) => return (None, Err(Exclusion::Unreadable(e.to_string()))),
+    };
+
+    let try_exec = entry
+        .desktop_entry("TryExec")
+        .filter(|binary| !binary.is_empty())
+        .map(|binary| (binary.to_string(), find_executable(binary).is_some()));
+
+    (try_exec, app_from_entry(&entry, file, settings))
+}
+
+fn app_from_entry(
+    entry: &DesktopEntry,
+    file: &DesktopFile,
+    settings: &ScanSettings,
+) -> Result<AppInfo, Exclusion> {
+    check_visibility(entry, &settings.desktops)?;
+    let locale = settings.locale.as_deref();
+
+    let name = entry
+        .name(locale)
+        .map(|cow| cow.to_string())
+        .ok_or(Exclusion::MissingKey("Name"))?;
+    let generic_name = entry
+        .generic_name(locale)
+        .map(|cow| cow.to_string())
+        .filter(|generic_name| !generic_name.is_empty());
+    let keywords = entry
+        .desktop_entry_localized("Keywords", locale)
+        .map(|cow| split_list(&cow))
+        .unwrap_or_default();
+    let description = entry
+        .comment(locale)
+        .map(|cow| cow.to_string())
+        .filter(|description| !description.is_empty());
+    let exec = entry.exec().ok_or(Exclusion::MissingKey("Exec"))?;
+    let icon = entry.icon();
+
+    let fields = ExecFields {
+        name: &name,
+        icon,
+        location: &file.path,
+    };
+
+    let (exe_path, args) = parse_exec(exec, &fields)
+        .and_then(split_command)
+        .map_err(|e| Exclusion::InvalidExec(e.to_string()))?;
+    let icon_path = icon.and_then(|icon| settings.icons.find(icon));
+    let actions = desktop_actions(entry, &fields, settings);
+
+    Ok(AppInfo {
+        id: file.id.clone(),
+        name,
+        generic_name,
+        keywords,
+        description,
+        exe_path,
+        args,
+        icon_path,
+        terminal: entry.terminal(),
+        actions,
+    })
+}
+
+/// Collect the `[Desktop Action ...]` groups listed in the entry's `Actions` key.
+/// Actions without a name or with an invalid `Exec` are skipped, and actions
+/// without an `Icon` of their own are shown with the application icon.
+fn desktop_actions(
+    entry: &DesktopEntry,
+    fields: &ExecFields,
+    settings: &ScanSettings,
+) -> Vec<AppAction> {
+    split_list(entry.actions().unwrap_or_default())
+        .into_iter()
+        .filter_map(|action| {
+            let name = entry
+                .action_name(&action, settings.locale.as_deref())?
+                .to_string();
+            let exec = entry.action_exec(&action)?;
+            let icon = entry.action_entry(&action, "Icon");
+
+            let action_fields = ExecFields {
+                icon: icon.or(fields.icon),
+                ..*fields
+            };
+            let (exe_path, args) = parse_exec(exec, &action_fields)
+                .and_then(split_command)
+                .ok()?;
+
+            Some(AppAction {
+                name,
+                exe_path,
+                args,
+                icon_path: icon.and_then(|icon| settings.icons.find(icon)),
+            })
         })
+        .collect()
 }
 
-fn resolve_icon_path(icon_name: &str) -> Option<PathBuf> {
-    // Handle absolute paths
-    if icon_name.starts_with('/') {
-        return PathBuf::from(icon_name).apply(|p| if p.exists() { Some(p) } else { None });
+/// Apply the Desktop Entry Specification rules that decide whether an entry is shown
+fn check_visibility(entry: &DesktopEntry, desktops: &[String]) -> Result<(), Exclusion> {
+    match entry.type_() {
+        Some("Application") | None => {}
+        Some(kind) => return Err(Exclusion::NotApplication(kind.to_string())),
     }
 
-    let base_dirs = get_icon_base_directories();
+    if entry.desktop_entry("Hidden") == Some("true") {
+        return Err(Exclusion::Hidden);
+    }
+    if entry.no_display() {
+        return Err(Exclusion::NoDisplay);
+    }
 
-    // Common icon theme directories
-    let icon_themes = [
-        "hicolor",         // Universal fallback (always present)
-        "Adwaita",         // GNOME, Fedora, many others
-        "breeze",          // KDE Plasma
-        "breeze-dark",     // KDE Plasma dark variant
-        "Yaru",            // Ubuntu
-        "Papirus",         // Popular third-party theme
-        "Pop",             // Pop!_OS
-        "Mint-Y",          // Linux Mint (Cinnamon)
-        "elementary-xfce", // XFCE
-    ];
+    if let Some(only) = entry.only_show_in()
+        && !desktop_list_matches(only, desktops)
+    {
+        return Err(Exclusion::OnlyShowIn(only.to_string()));
+    }
+    if let Some(not) = entry.not_show_in()
+        && desktop_list_matches(not, desktops)
+    {
+        return Err(Exclusion::NotShowIn(not.to_string()));
+    }
 
-    // Common sizes (prioritize scalable SVG, then common PNG sizes)
-    let sizes = ["scalable", "48x48", "64x64", "32x32", "24x24", "16x16"];
+    if let Some(try_exec) = entry.desktop_entry("TryExec").filter(|t| !t.is_empty())
+        && find_executable(try_exec).is_none()
+    {
+        return Err(Exclusion::TryExecMissing(try_exec.to_string()));
+    }
 
-    // Categories where app icons are found
-    let categories = ["apps", "places", "actions", "categories"];
+    Ok(())
+}
 
-    // Supported extensions (prioritize SVG, fallback to PNG)
-    let extensions = ["svg", "png", "xpm"];
+/// Split a `;`-separated desktop entry list such as `Keywords` or `Actions`
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(';')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
 
-    // Search themed icon directories
-    for base_dir in &base_dirs {
-        let base_path = PathBuf::from(base_dir);
-        if !base_path.exists() {
-            continue;
+/// Locale used for localized keys, from `LC_ALL`, `LC_MESSAGES` or `LANG` in that order.
+/// The encoding and modifier are dropped, so `de_DE.UTF-8@euro` becomes `de_DE`;
+/// lookups fall back from `de_DE` to `de` before the untranslated value.
+fn current_locale() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .map(|value| {
+            value
+                .split(['.', '@'])
+                .next()
+                .unwrap_or_default()
+                .to_string()
+        })
+        .filter(|locale| !locale.is_empty() && locale != "C" && locale != "POSIX")
+}
+
+/// Desktop environments named in `XDG_CURRENT_DESKTOP`, e.g. `["ubuntu", "GNOME"]`
+fn current_desktops() -> Vec<String> {
+    env::var("XDG_CURRENT_DESKTOP")
+        .unwrap_or_default()
+        .split(':')
+        .filter(|desktop| !desktop.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn desktops_label() -> String {
+    match current_desktops().join(":") {
+        desktops if desktops.is_empty() => "an unknown desktop".to_string(),
+        desktops => desktops,
+    }
+}
+
+/// Whether any current desktop appears in a `;`-separated `OnlyShowIn`/`NotShowIn` list
+fn desktop_list_matches(list: &str, desktops: &[String]) -> bool {
+    list.split(';')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .any(|name| {
+            desktops
+                .iter()
+                .any(|desktop| desktop.eq_ignore_ascii_case(name))
+        })
+}
+
+/// Values substituted for field codes when expanding an `Exec` line
+#[derive(Clone, Copy)]
+struct ExecFields<'a> {
+    /// Translated `Name` of the entry, used for `%c`
+    name: &'a str,
+    /// `Icon` key of the entry, used for `%i`
+    icon: Option<&'a str>,
+    /// Location of the desktop file, used for `%k`
+    location: &'a Path,
+}
+
+/// Piece of an `Exec` argument, before field codes are expanded
+enum Segment {
+    Literal(String),
+    FieldCode(char),
+}
+
+/// Parse an `Exec` value into an argv following the Desktop Entry Specification.
+///
+/// Arguments are split on unquoted whitespace, double-quoted arguments honour the
+/// `\"`, `` \` ``, `\$` and `\\` escapes, and field codes are expanded or dropped
+/// since the launcher never passes files or URLs. Deprecated and unknown field
+/// codes make the whole line invalid.
+fn parse_exec(exec: &str, fields: &ExecFields) -> Result<Vec<String>, Box<dyn Error>> {
+    let exec = unescape_value(exec);
+    let mut args: Vec<Vec<Segment>> = Vec::new();
+    let mut current: Option<Vec<Segment>> = None;
+    let mut in_quotes = false;
+    let mut chars = exec.chars();
+
+    let push_char = |current: &mut Option<Vec<Segment>>, c: char| {
+        let segments = current.get_or_insert_with(Vec::new);
+        match segments.last_mut() {
+            Some(Segment::Literal(text)) => text.push(c),
+            _ => segments.push(Segment::Literal(c.to_string())),
         }
+    };
 
-        // Skip pixmaps for themed search
-        if base_dir.ends_with("pixmaps") {
-            continue;
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                current.get_or_insert_with(Vec::new);
+            }
+            '\\' if in_quotes => match chars.next() {
+                Some(escaped @ ('"' | '`' | '$' | '\\')) => push_char(&mut current, escaped),
+                Some(other) => {
+                    push_char(&mut current, '\\');
+                    push_char(&mut current, other);
+                }
+                None => return Err("Unterminated escape sequence in Exec".into()),
+            },
+            '\\' => match chars.next() {
+                Some(escaped) => push_char(&mut current, escaped),
+                None => return Err("Unterminated escape sequence in Exec".into()),
+            },
+            '%' => match chars.next() {
+                Some('%') => push_char(&mut current, '%'),
+                Some(code) => current
+                    .get_or_insert_with(Vec::new)
+                    .push(Segment::FieldCode(code)),
+                None => return Err("Incomplete field code in Exec".into()),
+            },
+            c if c.is_whitespace() && !in_quotes => {
+                if let Some(segments) = current.take() {
+                    args.push(segments);
+                }
+            }
+            c => push_char(&mut current, c),
         }
+    }
 
-        for theme in icon_themes {
-            for size in sizes {
-                for category in categories {
-                    for ext in extensions {
-                        let path = base_path
-                            .join(theme)
-                            .join(size)
-                            .join(category)
-                            .join(format!("{}.{}", icon_name, ext));
-
-                        if path.exists() {
-                            return Some(path);
+    if in_quotes {
+        return Err("Unterminated quote in Exec".into());
+    }
+    if let Some(segments) = current.take() {
+        args.push(segments);
+    }
+
+    let mut argv = Vec::new();
+    for segments in args {
+        match segments.as_slice() {
+            // Standalone field codes may expand to zero or several arguments
+            [Segment::FieldCode(code)] => match code {
+                'f' | 'F' | 'u' | 'U' => {}
+                'i' => {
+                    if let Some(icon) = fields.icon {
+                        argv.push("--icon".to_string());
+                        argv.push(icon.to_string());
+                    }
+                }
+                code => argv.push(expand_field_code(*code, fields)?),
+            },
+            segments => {
+                let mut arg = String::new();
+                for segment in segments {
+                    match segment {
+                        Segment::Literal(text) => arg.push_str(text),
+                        Segment::FieldCode(code) => {
+                            arg.push_str(&expand_field_code(*code, fields)?)
                         }
                     }
                 }
+                argv.push(arg);
             }
         }
     }
 
-    // Fallback: search pixmaps directory directly
-    for base_dir in &base_dirs {
-        if base_dir.ends_with("pixmaps") {
-            for ext in extensions {
-                let path = PathBuf::from(base_dir).join(format!("{}.{}", icon_name, ext));
-                if path.exists() {
-                    return Some(path);
-                }
+    if argv.is_empty() {
+        return Err("Empty Exec command".into());
+    }
+
+    Ok(argv)
+}
+
+/// Apply the general string escapes (`\s`, `\n`, `\t`, `\r`, `\\`) of desktop entry values
+fn unescape_value(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('s') => result.push(' '),
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('r') => result.push('\r'),
+            Some('\\') => result.push('\\'),
+            Some(other) => {
+                result.push('\\');
+                result.push(other);
             }
+            None => result.push('\\'),
         }
     }
 
-    None
+    result
 }
 
-fn get_icon_base_directories() -> Vec<String> {
-    let mut base_dirs = Vec::new();
-
-    // System directories from XDG_DATA_DIRS (defaults to /usr/local/share:/usr/share)
-    env::var("XDG_DATA_DIRS")
-        .unwrap_or_else(|_| "/usr/local/share:/usr/share".to_string())
-        .split(':')
-        .filter(|dir| !dir.is_empty())
-        .for_each(|dir| base_dirs.push(format!("{}/icons", dir)));
+/// Expand a field code that is embedded in a larger argument
+fn expand_field_code(code: char, fields: &ExecFields) -> Result<String, Box<dyn Error>> {
+    match code {
+        'f' | 'F' | 'u' | 'U' => Ok(String::new()),
+        'i' => Ok(fields.icon.unwrap_or_default().to_string()),
+        'c' => Ok(fields.name.to_string()),
+        'k' => Ok(fields.location.to_string_lossy().into_owned()),
+        'd' | 'D' | 'n' | 'N' | 'v' | 'm' => {
+            Err(format!("Deprecated field code %{} in Exec", code).into())
+        }
+        _ => Err(format!("Invalid field code %{} in Exec", code).into()),
+    }
+}
 
-    // Legacy pixmaps directory
-    base_dirs.push("/usr/share/pixmaps".to_string());
+/// Split an argv into the resolved executable path and its arguments
+fn split_command(mut argv: Vec<String>) -> Result<(PathBuf, Vec<String>), Box<dyn Error>> {
+    let command = argv.remove(0);
+    Ok((resolve_executable(&command)?, argv))
+}
 
-    // User directories
-    if let Ok(home) = env::var("HOME") {
-        // XDG_DATA_HOME or default to ~/.local/share
-        let data_home =
-            env::var("XDG_DATA_HOME").unwrap_or_else(|_| format!("{}/.local/share", home));
-        base_dirs.push(format!("{}/icons", data_home));
-        base_dirs.push(format!("{}/.icons", home)); // Legacy
+fn resolve_executable(command: &str) -> Result<PathBuf, Box<dyn Error>> {
+    if command.starts_with('/') {
+        return Ok(PathBuf::from(command));
     }
+    find_executable(command)
+        .unwrap_or_else(|| PathBuf::from(command))
+        .apply(Ok)
+}
 
+/// Every directory icons are resolved from: theme base directories and pixmaps
+fn get_icon_base_directories() -> Vec<PathBuf> {
+    let mut base_dirs = icon_theme::base_directories();
+    base_dirs.push(PathBuf::from(icon_theme::PIXMAPS_DIR));
     base_dirs
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::IconConfig;
+
+    fn fields() -> ExecFields<'static> {
+        ExecFields {
+            name: "Test App",
+            icon: Some("test-icon"),
+            location: Path::new("/usr/share/applications/test.desktop"),
+        }
+    }
+
+    fn parse(exec: &str) -> Vec<String> {
+        parse_exec(exec, &fields()).expect("Exec should parse")
+    }
+
+    #[test]
+    fn test_desktop_file_id() {
+        let dir = Path::new("/usr/share/applications");
+        assert_eq!(
+            desktop_file_id(dir, &dir.join("firefox.desktop")).as_deref(),
+            Some("firefox.desktop")
+        );
+        assert_eq!(
+            desktop_file_id(dir, &dir.join("kde/konsole.desktop")).as_deref(),
+            Some("kde-konsole.desktop")
+        );
+        assert_eq!(desktop_file_id(dir, Path::new("/opt/app.desktop")), None);
+    }
+
+    #[test]
+    fn test_desktop_list_matches() {
+        let desktops = vec!["ubuntu".to_string(), "GNOME".to_string()];
+        assert!(desktop_list_matches("GNOME;", &desktops));
+        assert!(desktop_list_matches("KDE;gnome;", &desktops));
+        assert!(!desktop_list_matches("KDE;XFCE;", &desktops));
+        assert!(!desktop_list_matches("GNOME;", &[]));
+    }
+
+    #[test]
+    fn test_exec_keeps_arguments() {
+        assert_eq!(
+            parse("flatpak run org.gimp.GIMP"),
+            ["flatpak", "run", "org.gimp.GIMP"]
+        );
+        assert_eq!(parse("env FOO=1 app"), ["env", "FOO=1", "app"]);
+        assert_eq!(parse("code --new-window"), ["code", "--new-window"]);
+    }
+
+    #[test]
+    fn test_exec_drops_file_and_url_codes() {
+        assert_eq!(parse("firefox %u"), ["firefox"]);
+        assert_eq!(parse("gimp-2.10 %U"), ["gimp-2.10"]);
+        assert_eq!(
+            parse("vlc --started-from-file %F"),
+            ["vlc", "--started-from-file"]
+        );
+        assert_eq!(parse("app --file=%f"), ["app", "--file="]);
+    }
+
+    #[test]
+    fn test_exec_expands_entry_codes() {
+        assert_eq!(parse("app %i"), ["app", "--icon", "test-icon"]);
+        assert_eq!(parse("app --title %c"), ["app", "--title", "Test App"]);
+        assert_eq!(
+            parse("app %k"),
+            ["app", "/usr/share/applications/test.desktop"]
+        );
+        assert_eq!(parse("app 100%%"), ["app", "100%"]);
+    }
+
+    #[test]
+    fn test_exec_quoting() {
+        assert_eq!(
+            parse(r#""/opt/My App/bin/app" --name "two words""#),
+            ["/opt/My App/bin/app", "--name", "two words"]
+        );
+        assert_eq!(
+            parse(r#"sh -c "echo \\"hi\\" \\$HOME""#),
+            ["sh", "-c", r#"echo "hi" $HOME"#]
+        );
+        assert_eq!(parse(r#"app """#), ["app", ""]);
+    }
+
+    #[test]
+    fn test_exec_rejects_invalid_lines() {
+        assert!(parse_exec("app %d", &fields()).is_err());
+        assert!(parse_exec("app %m", &fields()).is_err());
+        assert!(parse_exec("app %z", &fields()).is_err());
+        assert!(parse_exec(r#"app "unterminated"#, &fields()).is_err());
+        assert!(parse_exec("   ", &fields()).is_err());
+    }
+
+    /// Settings independent of the user's desktop and icon theme
+    fn settings() -> ScanSettings {
+        let config = IconConfig {
+            theme: Some("hicolor".to_string()),
+            scale: Some(1),
+            ..IconConfig::default()
+        };
+        ScanSettings {
+            desktops: Vec::new(),
+            locale: None,
+            icons: IconTheme::new(&config, apps::ICON_SIZE, &[]),
+        }
+    }
+
+    fn actions(content: &str) -> Vec<AppAction> {
+        let entry = DesktopEntry::decode(fields().location, content).expect("entry should parse");
+        desktop_actions(&entry, &fields(), &settings())
+    }
+
+    #[test]
+    fn test_desktop_actions_follow_actions_key() {
+        let listed = actions(
+            "[Desktop Entry]\nName=Test App\nExec=/usr/bin/app\nActions=private;new-window;\n\n\
+             [Desktop Action new-window]\nName=New Window\nExec=/usr/bin/app --new-window\n\n\
+             [Desktop Action private]\nName=New Private Window\nExec=/usr/bin/app --private\n\n\
+             [Desktop Action unlisted]\nName=Unlisted\nExec=/usr/bin/app --unlisted\n",
+        );
+
+        let names: Vec<&str> = listed.iter().map(|action| action.name.as_str()).collect();
+        assert_eq!(names, ["New Private Window", "New Window"]);
+        assert_eq!(listed[0].exe_path, PathBuf::from("/usr/bin/app"));
+        assert_eq!(listed[0].args, ["--private"]);
+    }
+
+    #[test]
+    fn test_desktop_actions_skip_incomplete_groups() {
+        // "missing" has no group, "no-exec" no Exec and "broken" an invalid one
+        let listed = actions(
+            "[Desktop Entry]\nName=Test App\nExec=/usr/bin/app\n\
+             Actions=missing;no-exec;broken;ok;\n\n\
+             [Desktop Action no-exec]\nName=No Exec\n\n\
+             [Desktop Action broken]\nName=Broken\nExec=/usr/bin/app %d\n\n\
+             [Desktop Action ok]\nName=Ok\nExec=/usr/bin/app --ok\n",
+        );
+
+        let names: Vec<&str> = listed.iter().map(|action| action.name.as_str()).collect();
+        assert_eq!(names, ["Ok"]);
+        assert!(actions("[Desktop Entry]\nName=Test App\nExec=/usr/bin/app\n").is_empty());
+    }
+
+    #[test]
+    fn test_desktop_action_icons() {
+        let dir = std::env::temp_dir().join(format!("launchdock-actions-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let own_icon = dir.join("compose.png");
+        fs::write(&own_icon, b"").unwrap();
+
+        let listed = actions(&format!(
+            "[Desktop Entry]\nName=Test App\nExec=/usr/bin/app\nIcon=test-icon\n\
+             Actions=compose;plain;\n\n\
+             [Desktop Action compose]\nName=Compose\nExec=/usr/bin/app %i\nIcon={}\n\n\
+             [Desktop Action plain]\nName=Plain\nExec=/usr/bin/app %i\n",
+            own_icon.display()
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+
+        // An action's own icon is resolved and passed for %i
+        assert_eq!(listed[0].icon_path.as_ref(), Some(&own_icon));
+        assert_eq!(listed[0].args, ["--icon", own_icon.to_str().unwrap()]);
+
+        // Without one, the UI shows the app icon and %i expands to the app's Icon
+        assert_eq!(listed[1].icon_path, None);
+        assert_eq!(listed[1].args, ["--icon", "test-icon"]);
+    }
+
+    #[test]
+    fn test_update_catalog() {
+        let dir = std::env::temp_dir().join(format!("launchdock-catalog-{}", std::process::id()));
+        let (app_dir, icon_dir) = (dir.join("applications"), dir.join("icons"));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(app_dir.join("kde")).unwrap();
+        fs::create_dir_all(&icon_dir).unwrap();
+
+        let mut apps = Vec::new();
+        let mut cache = DiscoveryCache::new(String::new());
+        let mut update = |apps: &mut Vec<AppInfo>, paths: &[PathBuf]| {
+            update_catalog(
+                apps,
+                paths,
+                &[app_dir.clone()],
+                &[icon_dir.clone()],
+                &settings(),
+                &mut cache,
+            )
+        };
+        let icon = icon_dir.join("alpha.png");
+        let entry = |name: &str| {
+            format!(
+                "[Desktop Entry]\nName={}\nExec=/usr/bin/alpha\nIcon={}\n",
+                name,
+                icon.display()
+            )
+        };
+
+        let alpha = app_dir.join("alpha.desktop");
+        fs::write(&alpha, entry("Alpha")).unwrap();
+        let changes = update(&mut apps, &[alpha.clone()]);
+        assert!(matches!(&changes[..], [CatalogChange::Added(app)] if app.name == "Alpha"));
+        assert_eq!(apps[0].icon_path, None);
+
+        fs::write(&alpha, entry("Alpha 2")).unwrap();
+        let changes = update(&mut apps, &[alpha.clone()]);
+        assert!(matches!(&changes[..], [CatalogChange::Updated(app)] if app.name == "Alpha 2"));
+
+        // A new folder of entries is reported as the folder
+        fs::write(app_dir.join("kde/beta.desktop"), entry("Beta")).unwrap();
+        let changes = update(&mut apps, &[app_dir.join("kde")]);
+        assert!(
+            matches!(&changes[..], [CatalogChange::Added(app)] if app.id == "kde-beta.desktop")
+        );
+
+        // Entries lacking their icon pick it up once it's installed
+        fs::write(&icon, b"").unwrap();
+        let changes = update(&mut apps, &[icon.clone()]);
+        assert_eq!(changes.len(), 2);
+        assert!(apps.iter().all(|app| app.icon_path.as_ref() == Some(&icon)));
+
+        // Unrelated paths and unchanged entries change nothing
+        assert!(update(&mut apps, &[dir.join("notes.txt")]).is_empty());
+        assert!(update(&mut apps, &[alpha.clone()]).is_empty());
+
+        // Hidden or removed entries leave the catalog
+        fs::write(&alpha, entry("Alpha") + "NoDisplay=true\n").unwrap();
+        fs::remove_file(app_dir.join("kde/beta.desktop")).unwrap();
+        let changes = update(
+            &mut apps,
+            &[alpha.clone(), app_dir.join("kde/beta.desktop")],
+        );
+        assert_eq!(changes.len(), 2);
+        assert!(
+            changes
+                .iter()
+                .all(|change| matches!(change, CatalogChange::Removed(_)))
+        );
+        assert!(apps.is_empty());
+
+        // The discovery cache keeps the hidden entry and forgets the removed one
+        let hidden = cache.get(&alpha, cache::mtime(&alpha).unwrap());
+        assert!(hidden.is_some_and(|entry| entry.result.is_err()));
+        assert!(!cache.prune(&HashSet::from([alpha.clone()])));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,4 +1,5 @@
-use crate::apps::AppInfo;
+use crate::apps::{AppInfo, SystemCommand};
+use crate::config::IconConfig;
 use icns::{IconFamily, IconType};
 use plist::Value;
 use rs_apply::Apply;
@@ -8,7 +9,7 @@ use std::io::BufReader;
 use std::path::PathBuf;
 
 pub fn discover_applications() -> Result<Vec<AppInfo>, Box<dyn Error>> {
-    discover_app_bundles().chain(special_commands()).collect()
+    discover_app_bundles().collect()
 }
 
 pub fn extract_icon(app: &AppInfo) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
@@ -42,36 +43,27 @@ pub fn extract_icon(app: &AppInfo) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
         .transpose()
 }
 
-fn special_commands() -> impl Iterator<Item = Result<AppInfo, Box<dyn Error>>> {
+pub fn system_commands(_icons: &IconConfig) -> Vec<SystemCommand> {
     const SYSTEM_ICON: &str =
         "/System/Library/CoreServices/CoreTypes.bundle/Contents/Resources/ToolbarAdvanced.icns";
 
     [
-       (
-           "Shutdown",
-           "osascript -e 'tell app \"System Events\" to shut down'",
-       ),
-       (
-           "Logout",
-           "osascript -e 'tell app \"System Events\" to log out'",
-       ),
-       (
-           "Restart",
-           "osascript -e 'tell app \"System Events\" to restart'",
-       ),
-       (
-           "Lock Screen",
-           "osascript -e 'tell application \"System Events\" to keystroke \"q\" using {command down, control down}'",
-       ),
-   ]
+        ("Shutdown", "tell app \"System Events\" to shut down"),
+        ("Logout", "tell app \"System Events\" to log out"),
+        ("Restart", "tell app \"System Events\" to restart"),
+        (
+            "Lock Screen",
+            "tell application \"System Events\" to keystroke \"q\" using {command down, control down}",
+        ),
+    ]
     .into_iter()
-    .map(|(name, command)| {
-        Ok(AppInfo {
-            name: name.to_string(),
-            exe_path: PathBuf::from(command),
-            icon_path: Some(PathBuf::from(SYSTEM_ICON)),
-        })
+    .map(|(name, script)| SystemCommand {
+        name: name.to_string(),
+        program: PathBuf::from("osascript"),
+        args: vec!["-e".to_string(), script.to_string()],
+        icon_path: Some(PathBuf::from(SYSTEM_ICON)),
     })
+    .collect()
 }
 
 fn app_directories() -> impl Iterator<Item = String> {
@@ -115,9 +107,11 @@ fn parse_app_bundle(app_path: &std::path::Path) -> Result<Option<AppInfo>, Box<d
     let icon_path = find_icns_icon(app_path, &app_name);
 
     Ok(Some(AppInfo {
+        id: app_path.to_string_lossy().into_owned(),
         name: app_name,
         exe_path: exe_path,
         icon_path: icon_path,
+        ..Default::default()
     }))
 }
 
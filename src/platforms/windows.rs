@@ -1,4 +1,5 @@
-use crate::apps::AppInfo;
+use crate::apps::{AppInfo, SystemCommand};
+use crate::config::IconConfig;
 use rs_apply::Apply;
 use std::error::Error;
 use std::fs;
@@ -6,10 +7,7 @@ use std::path::PathBuf;
 use winreg::{RegKey, enums::*};
 
 pub fn discover_applications() -> Result<Vec<AppInfo>, Box<dyn Error>> {
-    registry_apps()
-        .chain(program_files_apps())
-        .chain(special_commands())
-        .collect()
+    registry_apps().chain(program_files_apps()).collect()
 }
 
 pub fn extract_icon(app: &AppInfo) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
@@ -20,24 +18,32 @@ pub fn extract_icon(app: &AppInfo) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
         .apply(Ok)
 }
 
-fn special_commands() -> impl Iterator<Item = Result<AppInfo, Box<dyn Error>>> {
+pub fn system_commands(_icons: &IconConfig) -> Vec<SystemCommand> {
     const SYSTEM_ICON: &str = "C:\\Windows\\System32\\shell32.dll";
 
     [
-        ("Shutdown", "shutdown /s /t 0"),
-        ("Logout", "shutdown /l"),
-        ("Restart", "shutdown /r /t 0"),
-        ("Sleep", "rundll32.exe powrprof.dll,SetSuspendState 0,1,0"),
-        ("Lock Screen", "rundll32.exe user32.dll,LockWorkStation"),
+        ("Shutdown", "shutdown", &["/s", "/t", "0"][..]),
+        ("Logout", "shutdown", &["/l"]),
+        ("Restart", "shutdown", &["/r", "/t", "0"]),
+        (
+            "Sleep",
+            "rundll32.exe",
+            &["powrprof.dll,SetSuspendState", "0,1,0"],
+        ),
+        (
+            "Lock Screen",
+            "rundll32.exe",
+            &["user32.dll,LockWorkStation"],
+        ),
     ]
     .into_iter()
-    .map(|(name, command)| {
-        Ok(AppInfo {
-            name: name.to_string(),
-            exe_path: PathBuf::from(command),
-            icon_path: Some(PathBuf::from(SYSTEM_ICON)),
-        })
+    .map(|(name, program, args)| SystemCommand {
+        name: name.to_string(),
+        program: PathBuf::from(program),
+        args: args.iter().map(|arg| arg.to_string()).collect(),
+        icon_path: Some(PathBuf::from(SYSTEM_ICON)),
     })
+    .collect()
 }
 
 fn registry_apps() -> impl Iterator<Item = Result<AppInfo, Box<dyn Error>>> {
@@ -76,12 +82,14 @@ fn parse_registry_entry(key: &RegKey) -> Result<Option<AppInfo>, Box<dyn Error>>
 
     match (name, install_location) {
         (Some(name), Some(install_location)) => AppInfo {
+            id: install_location.clone(),
             name,
             exe_path: PathBuf::from(&install_location),
             icon_path: key
                 .get_value::<String, _>("DisplayIcon")
                 .ok()
                 .map(PathBuf::from),
+            ..Default::default()
         }
         .apply(Some)
         .apply(Ok),
@@ -95,9 +103,11 @@ fn directory_to_app(dir: &std::path::Path) -> Result<Option<AppInfo>, Box<dyn Er
         .find(|entry| entry.path().extension() == Some(std::ffi::OsStr::new("exe")))
         .and_then(|entry| {
             dir.file_name().map(|name| AppInfo {
+                id: entry.path().to_string_lossy().into_owned(),
                 name: name.to_string_lossy().into_owned(),
                 exe_path: entry.path(),
                 icon_path: Some(entry.path()),
+                ..Default::default()
             })
         })
         .apply(Ok)
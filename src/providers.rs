@@ -0,0 +1,130 @@
+use std::path::PathBuf;
+
+mod apps;
+mod calculator;
+mod files;
+mod script;
+mod system;
+mod timezones;
+mod units;
+
+pub use apps::AppProvider;
+pub use calculator::CalculatorProvider;
+pub use files::FileProvider;
+pub use script::Script;
+pub use system::SystemProvider;
+pub use timezones::TimeZoneProvider;
+pub use units::UnitProvider;
+
+/// Score of direct answers like calculations and conversions, which are
+/// listed above any match
+const ANSWER_SCORE: f32 = f32::MAX;
+
+/// Source of launcher results. The UI asks every provider for each query and
+/// merges their items by score.
+pub trait Provider {
+    /// Best items for `query`, at most `limit` of them
+    fn query(&mut self, query: &str, limit: usize) -> Vec<ResultItem>;
+}
+
+/// One row of the results list
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResultItem {
+    pub title: String,
+    /// Matched character positions in the title
+    pub title_matches: Vec<usize>,
+    /// Parts of the smaller line below the title, with their matched character positions
+    pub subtitle: Vec<(String, Vec<usize>)>,
+    /// Icon file; a pattern generated from the title is shown without one
+    pub icon_path: Option<PathBuf>,
+    /// Relevance on the scale of application match scores, so that items of
+    /// all providers sort together
+    pub score: f32,
+    /// Only matched after correcting typos; listed after every other item
+    pub approximate: bool,
+    pub action: Action,
+}
+
+/// What activating a result does
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    /// Launch the application at this index of the `AppProvider` catalog
+    App(usize),
+    /// Run a program directly, without a shell
+    Run { program: PathBuf, args: Vec<String> },
+    /// Open a URI or file with the desktop's default handler
+    Open(String),
+    /// Copy text to the clipboard
+    Copy(String),
+    /// Open a local file or folder with its default application, or show it
+    /// in the file manager
+    File(PathBuf),
+}
+
+/// Ways to read a conversion query like `10 km in mi` as what to convert and
+/// what to convert it to, split at an "in", "to", "as" or arrow between
+/// words. The rightmost split comes first, so `3 in in cm` still finds the
+/// inches.
+fn conversion_parts(query: &str) -> Vec<(String, String)> {
+    let words: Vec<&str> = query.split_whitespace().collect();
+    (1..words.len().saturating_sub(1))
+        .rev()
+        .filter(|&i| {
+            ["in", "to", "as", "->", "→"]
+                .iter()
+                .any(|separator| words[i].eq_ignore_ascii_case(separator))
+        })
+        .map(|i| (words[..i].join(" "), words[i + 1..].join(" ")))
+        .collect()
+}
+
+/// Items of all providers in display order: best score first, typo-tolerant
+/// matches last. The sort is stable, so equal scores keep each provider's order.
+pub fn merge(mut items: Vec<ResultItem>, limit: usize) -> Vec<ResultItem> {
+    items.sort_by(|a, b| {
+        a.approximate
+            .cmp(&b.approximate)
+            .then(b.score.total_cmp(&a.score))
+    });
+    items.truncate(limit);
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(title: &str, score: f32, approximate: bool) -> ResultItem {
+        ResultItem {
+            title: title.to_string(),
+            title_matches: Vec::new(),
+            subtitle: Vec::new(),
+            icon_path: None,
+            score,
+            approximate,
+            action: Action::App(0),
+        }
+    }
+
+    #[test]
+    fn test_merge() {
+        let items = vec![
+            item("Firefox", 2.0, false),
+            item("Files", 0.0, true),
+            item("Shutdown", 3.0, false),
+            item("Favorite", 0.0, false),
+            item("Recent", 0.0, false),
+        ];
+
+        let titles = |items: Vec<ResultItem>| -> Vec<String> {
+            items.into_iter().map(|item| item.title).collect()
+        };
+
+        // Best score first, ties in the order given, typo-tolerant matches last
+        assert_eq!(
+            titles(merge(items.clone(), 10)),
+            ["Shutdown", "Firefox", "Favorite", "Recent", "Files"]
+        );
+        assert_eq!(titles(merge(items, 2)), ["Shutdown", "Firefox"]);
+    }
+}
@@ -0,0 +1,795 @@
+use super::{Action, Provider, ResultItem};
+use crate::apps::AppInfo;
+use crate::config::{Config, EmptyQueryConfig};
+use crate::favorites::Favorites;
+use crate::history::History;
+use crate::logs;
+use crate::search::{self, Highlight, SearchFields, SearchIndex};
+
+/// Below this many matches, typo-tolerant matches are listed after them
+const FEW_RESULTS: usize = 3;
+
+// Ranking boost per natural-log unit of decayed launches, and of earlier
+// launches from a query sharing a prefix with the current one
+const FRECENCY_WEIGHT: f32 = 0.15;
+const QUERY_CHOICE_WEIGHT: f32 = 0.3;
+
+/// Multiplier applied to a match score for an app's launch history.
+/// Logarithmic, so a daily app wins close calls without burying better matches.
+fn history_boost(frecency: f32, query_choices: usize) -> f32 {
+    1.0 + FRECENCY_WEIGHT * frecency.ln_1p() + QUERY_CHOICE_WEIGHT * (query_choices as f32).ln_1p()
+}
+
+/// Installed applications, ranked by match and launch history. Before anything
+/// is typed, lists pinned favorites and recently launched apps instead.
+pub struct AppProvider {
+    apps: Vec<AppInfo>,
+    /// `apps` folded for matching, in the same order
+    index: SearchIndex,
+    history: History,
+    favorites: Favorites,
+    empty_query: EmptyQueryConfig,
+    /// Query tokens `matches` were found for; empty after an empty query
+    matched_tokens: Vec<Vec<char>>,
+    /// Indices of the apps matching `matched_tokens`. When the query is
+    /// refined, only these are rescored.
+    matches: Vec<usize>,
+}
+
+impl AppProvider {
+    pub fn new(
+        apps: Vec<AppInfo>,
+        config: &Config,
+        history: History,
+        favorites: Favorites,
+    ) -> Self {
+        let index = SearchIndex::new(
+            apps.iter().map(|app| SearchFields {
+                name: &app.name,
+                generic_name: app.generic_name.as_deref(),
+                keywords: &app.keywords,
+                description: app.description.as_deref(),
+                aliases: config.aliases.get(&app.id).map_or(&[], Vec::as_slice),
+            }),
+            config.search.strip_diacritics,
+        );
+
+        Self {
+            apps,
+            index,
+            history,
+            favorites,
+            empty_query: config.empty_query.clone(),
+            matched_tokens: Vec::new(),
+            matches: Vec::new(),
+        }
+    }
+
+    /// The app an `Action::App` item launches
+    pub fn app(&self, index: usize) -> &AppInfo {
+        &self.apps[index]
+    }
+
+    pub fn is_favorite(&self, index: usize) -> bool {
+        self.favorites.contains(&self.apps[index].id)
+    }
+
+    /// Pin the app to the empty-query list, or unpin it
+    pub fn toggle_favorite(&mut self, index: usize) {
+        let app = &self.apps[index];
+
+        let pinned = self.favorites.toggle(&app.id);
+        logs::log_info(&format!(
+            "{}: {} [{}]",
+            if pinned { "Pinned" } else { "Unpinned" },
+            app.name,
+            app.id
+        ));
+        if let Err(e) = self.favorites.save() {
+            logs::log_error(&format!("Failed to save favorites: {}", e));
+        }
+    }
+
+    /// Pinned favorites followed by recently launched apps, as configured
+    fn empty_query_apps(&self) -> Vec<usize> {
+        let settings = &self.empty_query;
+        let find = |id: &str| self.apps.iter().position(|app| app.id == id);
+
+        let favorites = self
+            .favorites
+            .ids()
+            .iter()
+            .filter(|_| settings.favorites)
+            .filter_map(|id| find(id));
+        let recent = self
+            .history
+            .recent()
+            .into_iter()
+            .filter(|_| settings.recent)
+            .filter(|id| !settings.favorites || !self.favorites.contains(id))
+            .filter_map(find);
+
+        favorites.chain(recent).collect()
+    }
+
+    /// Results for `query`: matches best first with their scores, then, if
+    /// there are only a few, apps that match after correcting a typo or two.
+    /// With `candidates`, other apps are known not to match and are skipped.
+    fn search(
+        &self,
+        query: &str,
+        tokens: &[Vec<char>],
+        candidates: Option<&[usize]>,
+    ) -> (Vec<(usize, f32)>, Vec<usize>) {
+        if query.is_empty() {
+            let apps = self.empty_query_apps();
+            return (
+                apps.into_iter().map(|index| (index, 0.0)).collect(),
+                Vec::new(),
+            );
+        }
+
+        let query_choices = self.history.query_choices(query);
+
+        // Every word of the query must match on its own, in any order and field
+        let mut matches = self.index.matches(tokens, candidates);
+        for (index, score) in &mut matches {
+            let app = &self.apps[*index];
+            let choices = query_choices.get(app.id.as_str()).copied().unwrap_or(0);
+            *score *= history_boost(self.history.frecency(&app.id), choices);
+        }
+
+        // Sort by score (highest first), ties in catalog order
+        matches.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+
+        let approximate = if matches.len() < FEW_RESULTS {
+            let exact: Vec<usize> = matches.iter().map(|&(index, _)| index).collect();
+            self.index.approximate(tokens, &exact)
+        } else {
+            Vec::new()
+        };
+
+        (matches, approximate)
+    }
+
+    fn item(
+        &self,
+        index: usize,
+        score: f32,
+        highlight: Highlight,
+        approximate: bool,
+    ) -> ResultItem {
+        let app = &self.apps[index];
+        ResultItem {
+            title: app.name.clone(),
+            title_matches: highlight.name,
+            subtitle: highlight.subtitle,
+            icon_path: app.icon_path.clone(),
+            score,
+            approximate,
+            action: Action::App(index),
+        }
+    }
+}
+
+impl Provider for AppProvider {
+    fn query(&mut self, query: &str, limit: usize) -> Vec<ResultItem> {
+        let tokens = self.index.tokens(query);
+        let candidates =
+            search::refines(&self.matched_tokens, &tokens).then_some(&self.matches[..]);
+        let (exact, approximate) = self.search(query, &tokens, candidates);
+
+        // Typo-tolerant results have no exact positions to highlight
+        let items = exact
+            .iter()
+            .take(limit)
+            .map(|&(index, score)| {
+                self.item(index, score, self.index.highlight(index, &tokens), false)
+            })
+            .chain(
+                approximate
+                    .iter()
+                    .map(|&index| self.item(index, 0.0, Highlight::default(), true)),
+            )
+            .take(limit)
+            .collect();
+
+        self.matches = exact.into_iter().map(|(index, _)| index).collect();
+        self.matched_tokens = if query.is_empty() { Vec::new() } else { tokens };
+        items
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history;
+    use std::path::PathBuf;
+
+    fn create_test_app(name: &str, path: &str) -> AppInfo {
+        AppInfo {
+            name: name.to_string(),
+            exe_path: PathBuf::from(path),
+            icon_path: None,
+            ..Default::default()
+        }
+    }
+
+    impl AppProvider {
+        /// Exact and typo-tolerant results of a full search for `query`
+        fn query_results(&self, query: &str) -> (Vec<&AppInfo>, Vec<&AppInfo>) {
+            let tokens = self.index.tokens(query);
+            let (exact, approximate) = self.search(query, &tokens, None);
+            let exact = exact.into_iter().map(|(index, _)| &self.apps[index]);
+            let approximate = approximate.into_iter().map(|index| &self.apps[index]);
+            (exact.collect(), approximate.collect())
+        }
+
+        /// All results in display order, typo-tolerant ones last
+        fn filtered_apps(&self, query: &str) -> Vec<&AppInfo> {
+            let (mut apps, approximate) = self.query_results(query);
+            apps.extend(approximate);
+            apps
+        }
+    }
+
+    fn create_test_provider(apps: Vec<AppInfo>) -> AppProvider {
+        AppProvider::new(
+            apps,
+            &Config::default(),
+            History::default(),
+            Favorites::default(),
+        )
+    }
+
+    #[test]
+    fn test_basic_matching() {
+        let apps = vec![
+            create_test_app("firefox", "/usr/bin/firefox"),
+            create_test_app("photogravure", "/usr/bin/photogravure"),
+            create_test_app("gimp", "/usr/bin/gimp"),
+            create_test_app("gnome-video", "/usr/bin/gnome-video"),
+        ];
+
+        let provider = create_test_provider(apps);
+        let results = provider.filtered_apps("gv");
+
+        // Should have exactly 2 results: gnome-video first, as both characters start
+        // a word, then photogravure despite its closer characters
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].name, "gnome-video");
+        assert_eq!(results[1].name, "photogravure");
+    }
+
+    #[test]
+    fn test_case_insensitive() {
+        let apps = vec![
+            create_test_app("Firefox", "/usr/bin/Firefox"),
+            create_test_app("GIMP", "/usr/bin/GIMP"),
+        ];
+
+        let provider = create_test_provider(apps);
+        let results = provider.filtered_apps("fox");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].name, "Firefox");
+
+        let results = provider.filtered_apps("GIM");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].name, "GIMP");
+    }
+
+    #[test]
+    fn test_secondary_fields() {
+        let apps = vec![
+            AppInfo {
+                generic_name: Some("Web Browser".to_string()),
+                keywords: vec!["Internet".to_string(), "WWW".to_string()],
+                ..create_test_app("Firefox", "/usr/bin/firefox")
+            },
+            AppInfo {
+                generic_name: Some("Image Editor".to_string()),
+                description: Some("Create images and edit photographs".to_string()),
+                ..create_test_app("GIMP", "/usr/bin/gimp")
+            },
+            create_test_app("Browser Tools", "/usr/bin/browser-tools"),
+        ];
+
+        let provider = create_test_provider(apps);
+        let results = provider.filtered_apps("browser");
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].name, "Browser Tools"); // Name match outranks GenericName
+        assert_eq!(results[1].name, "Firefox");
+
+        let results = provider.filtered_apps("image editor");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].name, "GIMP");
+
+        assert_eq!(provider.filtered_apps("www")[0].name, "Firefox");
+        assert_eq!(provider.filtered_apps("photograph")[0].name, "GIMP");
+    }
+
+    #[test]
+    fn test_history_ranking() {
+        let apps = vec![
+            AppInfo {
+                id: "files".to_string(),
+                ..create_test_app("Files", "/usr/bin/nautilus")
+            },
+            AppInfo {
+                id: "firefox".to_string(),
+                ..create_test_app("Firefox", "/usr/bin/firefox")
+            },
+            AppInfo {
+                id: "fish".to_string(),
+                ..create_test_app("fish", "/usr/bin/fish")
+            },
+        ];
+        let launch = |app_id: &str, query: &str| history::LaunchRecord {
+            timestamp: 0,
+            app_id: app_id.to_string(),
+            app_name: app_id.to_string(),
+            query: query.to_string(),
+        };
+
+        // Without history "fish" wins on its shorter name
+        let mut provider = create_test_provider(apps);
+        assert_eq!(provider.filtered_apps("fi")[0].name, "fish");
+
+        provider.history = History::new(vec![launch("firefox", "fire"); 5], 0);
+        assert_eq!(provider.filtered_apps("fi")[0].name, "Firefox");
+
+        // Launches from unrelated queries still count, but less
+        let frequent = History::new(vec![launch("firefox", "browser"); 5], 0);
+        let chosen = History::new(vec![launch("firefox", "fir"); 5], 0);
+        assert!(history_boost(frequent.frecency("firefox"), 0) > 1.0);
+        assert!(
+            history_boost(
+                chosen.frecency("firefox"),
+                chosen.query_choices("fi")["firefox"]
+            ) > history_boost(frequent.frecency("firefox"), 0)
+        );
+
+        // A single launch from an unrelated query doesn't outrank a better match
+        provider.history = History::new(vec![launch("firefox", "browser")], 0);
+        assert_eq!(provider.filtered_apps("fi")[0].name, "fish");
+    }
+
+    #[test]
+    fn test_aliases() {
+        let apps = vec![
+            AppInfo {
+                id: "tracker".to_string(),
+                ..create_test_app("Tracker", "/opt/tracker")
+            },
+            AppInfo {
+                id: "jira".to_string(),
+                ..create_test_app("Jira", "/usr/bin/jira")
+            },
+            AppInfo {
+                id: "firefox".to_string(),
+                ..create_test_app("Firefox", "/usr/bin/firefox")
+            },
+            create_test_app("Fluffy Fish", "/usr/bin/fluffy-fish"),
+        ];
+        let mut config = Config::default();
+        config
+            .aliases
+            .insert("tracker".to_string(), vec!["JIRA".to_string()]);
+        config
+            .aliases
+            .insert("firefox".to_string(), vec!["ff".to_string()]);
+
+        let mut provider =
+            AppProvider::new(apps, &config, History::default(), Favorites::default());
+
+        // A whole alias beats an exact name and a better-scoring acronym
+        assert_eq!(provider.filtered_apps("jira")[0].name, "Tracker");
+        assert_eq!(provider.filtered_apps("ff")[0].name, "Firefox");
+        assert_eq!(provider.filtered_apps("ff tab").len(), 0);
+
+        // ...even against an app launched often from the same query
+        let launch = history::LaunchRecord {
+            timestamp: 0,
+            app_id: "jira".to_string(),
+            app_name: "Jira".to_string(),
+            query: "jira".to_string(),
+        };
+        provider.history = History::new(vec![launch; 50], 0);
+        assert_eq!(provider.filtered_apps("jira")[0].name, "Tracker");
+
+        // A partial alias matches like a name, without the bonus, and is shown
+        let items = provider.query("ji", 7);
+        assert_eq!(items.len(), 2);
+        let tracker = items.iter().find(|item| item.title == "Tracker").unwrap();
+        assert!(tracker.title_matches.is_empty());
+        assert_eq!(tracker.subtitle, vec![("JIRA".to_string(), vec![0, 1])]);
+    }
+
+    #[test]
+    fn test_unicode_case_folding() {
+        let apps = vec![
+            create_test_app("Écran", "/usr/bin/ecran"),
+            create_test_app("Größe", "/usr/bin/groesse"),
+            create_test_app("Файлы", "/usr/bin/files"),
+            create_test_app("ΟΔΥΣΣΕΥΣ", "/usr/bin/odysseus"),
+            create_test_app("ﬁle manager", "/usr/bin/fm"),
+        ];
+
+        let provider = create_test_provider(apps.clone());
+        let names = |query: &str| -> Vec<String> {
+            provider
+                .filtered_apps(query)
+                .iter()
+                .map(|app| app.name.clone())
+                .collect()
+        };
+
+        assert_eq!(names("écran"), ["Écran"]);
+        assert_eq!(names("ecran"), ["Écran"]); // Diacritics stripped by default
+        assert_eq!(names("GROSSE"), ["Größe"]); // ß folds to ss
+        assert_eq!(names("größe"), ["Größe"]);
+        assert_eq!(names("файл"), ["Файлы"]);
+        assert_eq!(names("ФАЙЛ"), ["Файлы"]);
+        assert_eq!(names("οδυσσευς"), ["ΟΔΥΣΣΕΥΣ"]); // Final sigma folds to σ
+        assert_eq!(names("file"), ["ﬁle manager"]); // Ligature decomposes
+
+        // Keeping diacritics, an accented query still matches but a plain one
+        // only as a typo
+        let mut config = Config::default();
+        config.search.strip_diacritics = false;
+        let provider = AppProvider::new(apps, &config, History::default(), Favorites::default());
+        assert!(provider.query_results("ecran").0.is_empty());
+        assert_eq!(provider.filtered_apps("ÉCRAN")[0].name, "Écran");
+    }
+
+    /// Typical desktop catalog for the ranking regression corpus
+    const CORPUS_APPS: &[&str] = &[
+        "Audacity",
+        "Blender",
+        "Calculator",
+        "Calendar",
+        "Chromium",
+        "Color Picker",
+        "Disk Usage Analyzer",
+        "Discord",
+        "Files",
+        "Firefox",
+        "GIMP",
+        "Gnome Video",
+        "GoodVibes",
+        "Google Chrome",
+        "Image Viewer",
+        "Inkscape",
+        "KeePassXC",
+        "LibreOffice",
+        "LibreOffice Calc",
+        "LibreOffice Writer",
+        "Obsidian",
+        "Photo Studio Gallery",
+        "Photoshop Graphics",
+        "Settings",
+        "Signal",
+        "Slack",
+        "Software",
+        "Spotify",
+        "Steam",
+        "System Monitor",
+        "Terminal",
+        "Text Editor",
+        "Thunderbird",
+        "Visual Studio",
+        "Visual Studio Code",
+        "VLC media player",
+    ];
+
+    /// Query and the app expected at the top of the results
+    const CORPUS: &[(&str, &str)] = &[
+        ("vsc", "Visual Studio Code"),
+        ("vs", "Visual Studio"),
+        ("code", "Visual Studio Code"),
+        ("lo", "LibreOffice"),
+        ("low", "LibreOffice Writer"),
+        ("loc", "LibreOffice Calc"),
+        ("gv", "GoodVibes"),
+        ("gnvi", "Gnome Video"),
+        ("psg", "Photo Studio Gallery"),
+        ("phg", "Photoshop Graphics"),
+        ("gc", "Google Chrome"),
+        ("chr", "Chromium"),
+        ("fx", "Firefox"),
+        ("fi", "Files"),
+        ("te", "Terminal"),
+        ("ted", "Text Editor"),
+        ("symo", "System Monitor"),
+        ("dua", "Disk Usage Analyzer"),
+        ("iv", "Image Viewer"),
+        ("kpx", "KeePassXC"),
+        ("tb", "Thunderbird"),
+        ("vlc", "VLC media player"),
+        ("calc", "Calculator"),
+        ("cal", "Calendar"),
+        ("cp", "Color Picker"),
+        ("sp", "Spotify"),
+        ("st", "Steam"),
+        ("sig", "Signal"),
+        ("disc", "Discord"),
+        ("ink", "Inkscape"),
+    ];
+
+    #[test]
+    fn test_ranking_corpus() {
+        let apps: Vec<AppInfo> = CORPUS_APPS
+            .iter()
+            .map(|name| create_test_app(name, "/usr/bin/app"))
+            .collect();
+        let provider = create_test_provider(apps);
+
+        let failures: Vec<String> = CORPUS
+            .iter()
+            .filter_map(|&(query, expected)| {
+                let top = provider
+                    .filtered_apps(query)
+                    .first()
+                    .map(|app| app.name.clone());
+                (top.as_deref() != Some(expected))
+                    .then(|| format!("{:?}: expected {:?}, got {:?}", query, expected, top))
+            })
+            .collect();
+
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+    }
+
+    #[test]
+    fn test_typo_tolerance() {
+        let apps = vec![
+            create_test_app("Thunderbird", "/usr/bin/thunderbird"),
+            create_test_app("Thunder Bridge", "/usr/bin/thunder-bridge"),
+            create_test_app("Firefox", "/usr/bin/firefox"),
+            create_test_app("Files", "/usr/bin/nautilus"),
+            create_test_app("Terminal", "/usr/bin/terminal"),
+        ];
+        let mut provider = create_test_provider(apps);
+
+        let search = |provider: &AppProvider, query: &str| -> (Vec<String>, Vec<String>) {
+            let (exact, approximate) = provider.query_results(query);
+            let names = |apps: Vec<&AppInfo>| apps.iter().map(|app| app.name.clone()).collect();
+            (names(exact), names(approximate))
+        };
+
+        // Transposed characters: the exact match is listed first
+        let (exact, approximate) = search(&provider, "thunderbrid");
+        assert_eq!(exact, ["Thunder Bridge"]);
+        assert_eq!(approximate, ["Thunderbird"]);
+
+        // Extra and mistyped characters
+        assert_eq!(
+            search(&provider, "fierfox"),
+            (vec![], vec!["Firefox".to_string()])
+        );
+        assert_eq!(
+            search(&provider, "termonal"),
+            (vec![], vec!["Terminal".to_string()])
+        );
+
+        // Too many typos, or too short a query to guess
+        assert_eq!(search(&provider, "xyzfirefox"), (vec![], vec![]));
+        assert_eq!(
+            search(&provider, "fiels"),
+            (vec![], vec!["Files".to_string()])
+        );
+        assert_eq!(search(&provider, "flse"), (vec![], vec![]));
+
+        // Missing characters already match as a subsequence
+        assert_eq!(search(&provider, "firfox").0, ["Firefox"]);
+
+        // Approximate results follow the exact ones in the listed items
+        let items = provider.query("thunderbrid", 7);
+        assert_eq!(items.len(), 2);
+        assert!(!items[0].approximate);
+        assert!(items[1].approximate);
+    }
+
+    #[test]
+    fn test_multi_token_queries() {
+        let apps = vec![
+            AppInfo {
+                generic_name: Some("Terminal emulator".to_string()),
+                ..create_test_app("kitty", "/usr/bin/kitty")
+            },
+            AppInfo {
+                generic_name: Some("Terminal emulator".to_string()),
+                ..create_test_app("Terminal", "/usr/bin/gnome-terminal")
+            },
+            AppInfo {
+                generic_name: Some("Image Editor".to_string()),
+                ..create_test_app("GIMP", "/usr/bin/gimp")
+            },
+            create_test_app("Image Viewer", "/usr/bin/eog"),
+            AppInfo {
+                keywords: vec!["Project".to_string(), "Editor".to_string()],
+                ..create_test_app("Visual Studio Code", "/usr/bin/code")
+            },
+        ];
+
+        let provider = create_test_provider(apps);
+        let names = |query: &str| -> Vec<String> {
+            provider
+                .query_results(query)
+                .0
+                .iter()
+                .map(|app| app.name.clone())
+                .collect()
+        };
+
+        // Tokens match across fields, in any order
+        assert_eq!(names("term kit"), ["kitty"]);
+        assert_eq!(names("kit  term"), ["kitty"]);
+        assert_eq!(names("gimp image"), ["GIMP"]);
+        assert_eq!(names("image")[0], "Image Viewer");
+        assert_eq!(names("code proj"), ["Visual Studio Code"]);
+        assert_eq!(names("proj code"), ["Visual Studio Code"]);
+
+        // Every token must match somewhere
+        assert!(names("term xyz").is_empty());
+        assert!(names("   ").is_empty());
+        assert_eq!(names(" term "), ["Terminal", "kitty"]);
+    }
+
+    #[test]
+    fn test_incremental_filtering() {
+        let apps: Vec<AppInfo> = CORPUS_APPS
+            .iter()
+            .map(|name| create_test_app(name, "/usr/bin/app"))
+            .collect();
+        let mut provider = create_test_provider(apps);
+        provider.query("", usize::MAX);
+
+        // Typing narrows the previous matches, deleting searches everything again;
+        // either way the results are those of a full search
+        let mut typed = String::new();
+        let mut items = Vec::new();
+        for edit in [
+            "l", "i", "b", "r", " ", "w", "\u{8}", "c", "\u{8}", "\u{8}", "\u{8}",
+        ] {
+            if edit == "\u{8}" {
+                typed.pop();
+            } else {
+                typed.push_str(edit);
+            }
+            items = provider.query(&typed, usize::MAX);
+
+            let listed: Vec<&str> = items.iter().map(|item| item.title.as_str()).collect();
+            let full: Vec<&str> = provider
+                .filtered_apps(&typed)
+                .iter()
+                .map(|app| app.name.as_str())
+                .collect();
+            assert_eq!(listed, full, "query {:?}", typed);
+        }
+        assert_eq!(typed, "lib");
+        assert!(items.len() >= 3);
+    }
+
+    #[test]
+    fn test_highlights() {
+        let apps = vec![
+            AppInfo {
+                generic_name: Some("Terminal emulator".to_string()),
+                keywords: vec!["shell".to_string(), "prompt".to_string()],
+                ..create_test_app("kitty", "/usr/bin/kitty")
+            },
+            AppInfo {
+                description: Some("Edit images and photographs".to_string()),
+                ..create_test_app("GIMP", "/usr/bin/gimp")
+            },
+        ];
+        let mut provider = create_test_provider(apps);
+
+        // Name matches highlight the name only
+        let kitty = provider.query("kty", 7).remove(0);
+        assert_eq!(kitty.title_matches, vec![0, 3, 4]);
+        assert!(kitty.subtitle.is_empty());
+
+        // Tokens matching secondary fields are listed with their positions
+        let kitty = provider.query("kit term shell", 7).remove(0);
+        assert_eq!(kitty.title_matches, vec![0, 1, 2]);
+        assert_eq!(
+            kitty.subtitle,
+            vec![
+                ("Terminal emulator".to_string(), vec![0, 1, 2, 3]),
+                ("shell".to_string(), vec![0, 1, 2, 3, 4]),
+            ]
+        );
+
+        let gimp = provider.query("photo", 7).remove(0);
+        assert!(gimp.title_matches.is_empty());
+        assert_eq!(
+            gimp.subtitle,
+            vec![(
+                "Edit images and photographs".to_string(),
+                vec![16, 17, 18, 19, 20]
+            )]
+        );
+
+        // Typo-tolerant results are not highlighted
+        let kitty = provider.query("kittyy", 7).remove(0);
+        assert!(kitty.approximate);
+        assert!(kitty.title_matches.is_empty() && kitty.subtitle.is_empty());
+    }
+
+    #[test]
+    fn test_empty_query() {
+        let apps = vec![
+            create_test_app("firefox", "/usr/bin/firefox"),
+            create_test_app("gimp", "/usr/bin/gimp"),
+        ];
+
+        let provider = create_test_provider(apps);
+        let results = provider.filtered_apps("");
+        assert_eq!(results.len(), 0); // Empty query returns no results
+    }
+
+    #[test]
+    fn test_empty_query_favorites_and_recent() {
+        let apps: Vec<AppInfo> = ["files", "firefox", "gimp", "terminal"]
+            .into_iter()
+            .map(|id| AppInfo {
+                id: id.to_string(),
+                ..create_test_app(id, &format!("/usr/bin/{}", id))
+            })
+            .collect();
+        let launch = |timestamp: i64, app_id: &str| history::LaunchRecord {
+            timestamp,
+            app_id: app_id.to_string(),
+            app_name: app_id.to_string(),
+            query: String::new(),
+        };
+
+        let mut provider = create_test_provider(apps);
+        provider.history = History::new(
+            vec![
+                launch(1, "gimp"),
+                launch(2, "firefox"),
+                launch(3, "uninstalled"),
+                launch(4, "gimp"),
+            ],
+            4,
+        );
+        provider.favorites.toggle("terminal");
+        provider.favorites.toggle("firefox");
+
+        let names = |provider: &AppProvider| -> Vec<String> {
+            provider
+                .filtered_apps("")
+                .iter()
+                .map(|app| app.name.clone())
+                .collect()
+        };
+
+        // Favorites in pinned order, then recent apps not already pinned
+        assert_eq!(names(&provider), ["terminal", "firefox", "gimp"]);
+
+        provider.empty_query.favorites = false;
+        assert_eq!(names(&provider), ["gimp", "firefox"]);
+
+        provider.empty_query.favorites = true;
+        provider.empty_query.recent = false;
+        assert_eq!(names(&provider), ["terminal", "firefox"]);
+    }
+
+    #[test]
+    fn test_no_matches() {
+        let apps = vec![
+            create_test_app("firefox", "/usr/bin/firefox"),
+            create_test_app("photogravure", "/usr/bin/photogravure"),
+            create_test_app("gimp", "/usr/bin/gimp"),
+            create_test_app("gnome-video", "/usr/bin/gnome-video"),
+        ];
+
+        let provider = create_test_provider(apps);
+        let results = provider.filtered_apps("xyz");
+
+        // Should have no results as no app contains x, y, z in sequence
+        assert_eq!(results.len(), 0);
+    }
+}
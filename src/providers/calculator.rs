@@ -0,0 +1,473 @@
+use super::{ANSWER_SCORE, Action, Provider, ResultItem};
+
+/// Evaluates arithmetic typed into the launcher, e.g. `=2*(3+4)/7` or `1024*3`.
+/// Without the leading `=`, only queries that parse as an expression with at
+/// least one operation are evaluated, so a plain number or app name isn't.
+/// Enter copies the result.
+pub struct CalculatorProvider;
+
+impl Provider for CalculatorProvider {
+    fn query(&mut self, query: &str, limit: usize) -> Vec<ResultItem> {
+        let (expression, explicit) = match query.strip_prefix('=') {
+            Some(expression) => (expression.trim(), true),
+            None => (query.trim(), false),
+        };
+        if limit == 0 || expression.is_empty() {
+            return Vec::new();
+        }
+
+        match evaluate(expression) {
+            Ok((value, operations)) if explicit || operations > 0 => {
+                let result = value.to_string();
+                vec![ResultItem {
+                    title: result.clone(),
+                    title_matches: Vec::new(),
+                    subtitle: vec![(expression.to_string(), Vec::new())],
+                    icon_path: None,
+                    score: ANSWER_SCORE,
+                    approximate: false,
+                    action: Action::Copy(result),
+                }]
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Integers stay exact until an operation needs a fraction or overflows
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Number {
+    Int(i64),
+    Float(f64),
+}
+
+impl Number {
+    fn float(self) -> f64 {
+        match self {
+            Number::Int(n) => n as f64,
+            Number::Float(x) => x,
+        }
+    }
+
+    /// Apply an operation exactly on integers, falling back to floats when
+    /// either side is one or the exact result doesn't fit
+    fn combine(
+        self,
+        other: Number,
+        int_op: fn(i64, i64) -> Option<i64>,
+        float_op: fn(f64, f64) -> f64,
+    ) -> Number {
+        match (self, other) {
+            (Number::Int(a), Number::Int(b)) => int_op(a, b)
+                .map(Number::Int)
+                .unwrap_or_else(|| Number::Float(float_op(a as f64, b as f64))),
+            (a, b) => Number::Float(float_op(a.float(), b.float())),
+        }
+    }
+
+    fn divide(self, other: Number) -> Result<Number, String> {
+        if other.float() == 0.0 {
+            return Err("division by zero".to_string());
+        }
+        Ok(self.combine(
+            other,
+            |a, b| {
+                if a.checked_rem(b) == Some(0) {
+                    a.checked_div(b)
+                } else {
+                    None
+                }
+            },
+            |a, b| a / b,
+        ))
+    }
+
+    fn remainder(self, other: Number) -> Result<Number, String> {
+        if other.float() == 0.0 {
+            return Err("division by zero".to_string());
+        }
+        Ok(self.combine(other, i64::checked_rem, |a, b| a % b))
+    }
+
+    fn power(self, other: Number) -> Number {
+        self.combine(
+            other,
+            |a, b| u32::try_from(b).ok().and_then(|b| a.checked_pow(b)),
+            f64::powf,
+        )
+    }
+
+    fn negate(self) -> Number {
+        match self {
+            Number::Int(n) => n
+                .checked_neg()
+                .map_or(Number::Float(-(n as f64)), Number::Int),
+            Number::Float(x) => Number::Float(-x),
+        }
+    }
+}
+
+impl std::fmt::Display for Number {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Number::Int(n) => write!(f, "{}", n),
+            Number::Float(x) => {
+                // 12 significant digits hide binary rounding, e.g. in 0.1 + 0.2
+                let rounded: f64 = format!("{:.11e}", x).parse().unwrap_or(*x);
+                write!(f, "{}", rounded)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(Number),
+    Name(String),
+    Operator(char),
+    Open,
+    Close,
+    Comma,
+}
+
+/// Split an expression into numbers, names and operators. Numbers may be
+/// decimal with an optional fraction and exponent, or `0x`, `0b` or `0o`
+/// prefixed integers.
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = expression.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        i += 1;
+
+        match c {
+            c if c.is_whitespace() => {}
+            '0' if i < chars.len() && matches!(chars[i], 'x' | 'X' | 'b' | 'B' | 'o' | 'O') => {
+                let radix = match chars[i].to_ascii_lowercase() {
+                    'x' => 16,
+                    'b' => 2,
+                    _ => 8,
+                };
+                i += 1;
+                let digits_start = i;
+                while i < chars.len() && chars[i].is_digit(radix) {
+                    i += 1;
+                }
+                let digits: String = chars[digits_start..i].iter().collect();
+                let n = i64::from_str_radix(&digits, radix)
+                    .map_err(|e| format!("invalid literal {:?}: {}", digits, e))?;
+                tokens.push(Token::Number(Number::Int(n)));
+            }
+            c if c.is_ascii_digit() || c == '.' => {
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                // An exponent needs digits, so "2e" leaves the "e" for a name
+                if i < chars.len() && matches!(chars[i], 'e' | 'E') {
+                    let sign = usize::from(matches!(chars.get(i + 1), Some('+' | '-')));
+                    if chars.get(i + 1 + sign).is_some_and(char::is_ascii_digit) {
+                        i += 1 + sign;
+                        while i < chars.len() && chars[i].is_ascii_digit() {
+                            i += 1;
+                        }
+                    }
+                }
+
+                let literal: String = chars[start..i].iter().collect();
+                let number = if literal.contains(['.', 'e', 'E']) {
+                    literal.parse().map(Number::Float).ok()
+                } else {
+                    literal
+                        .parse()
+                        .map(Number::Int)
+                        .or_else(|_| literal.parse().map(Number::Float))
+                        .ok()
+                };
+                tokens.push(Token::Number(
+                    number.ok_or_else(|| format!("invalid number {:?}", literal))?,
+                ));
+            }
+            c if c.is_alphabetic() => {
+                while i < chars.len() && chars[i].is_alphanumeric() {
+                    i += 1;
+                }
+                let name: String = chars[start..i].iter().collect();
+                tokens.push(Token::Name(name.to_lowercase()));
+            }
+            // "**" is another way to write "^"
+            '*' if chars.get(i) == Some(&'*') => {
+                i += 1;
+                tokens.push(Token::Operator('^'));
+            }
+            '+' | '-' | '*' | '/' | '%' | '^' => tokens.push(Token::Operator(c)),
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            ',' => tokens.push(Token::Comma),
+            _ => return Err(format!("unexpected {:?}", c)),
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Evaluate `expression`, returning its value and how many operators and
+/// functions it applied
+fn evaluate(expression: &str) -> Result<(Number, usize), String> {
+    let mut parser = Parser {
+        tokens: tokenize(expression)?,
+        position: 0,
+        operations: 0,
+    };
+
+    let value = parser.expression()?;
+    if let Some(token) = parser.peek() {
+        return Err(format!("unexpected {:?}", token));
+    }
+    if !value.float().is_finite() {
+        return Err("result is not a finite number".to_string());
+    }
+
+    Ok((value, parser.operations))
+}
+
+/// Recursive descent over the grammar, lowest precedence first:
+///
+/// ```text
+/// expression = term (("+" | "-") term)*
+/// term       = unary (("*" | "/" | "%") unary)*
+/// unary      = ("+" | "-") unary | power
+/// power      = primary ("^" unary)?
+/// primary    = number | name | name "(" arguments ")" | "(" expression ")"
+/// ```
+///
+/// so `-2^2` is -4 and `2^3^2` is 512.
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    operations: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(format!("expected {:?}, found {:?}", expected, token)),
+            None => Err(format!("expected {:?}", expected)),
+        }
+    }
+
+    fn expression(&mut self) -> Result<Number, String> {
+        let mut value = self.term()?;
+        while let Some(&Token::Operator(op @ ('+' | '-'))) = self.peek() {
+            self.position += 1;
+            let rhs = self.term()?;
+            self.operations += 1;
+            value = if op == '+' {
+                value.combine(rhs, i64::checked_add, |a, b| a + b)
+            } else {
+                value.combine(rhs, i64::checked_sub, |a, b| a - b)
+            };
+        }
+        Ok(value)
+    }
+
+    fn term(&mut self) -> Result<Number, String> {
+        let mut value = self.unary()?;
+        while let Some(&Token::Operator(op @ ('*' | '/' | '%'))) = self.peek() {
+            self.position += 1;
+            let rhs = self.unary()?;
+            self.operations += 1;
+            value = match op {
+                '*' => value.combine(rhs, i64::checked_mul, |a, b| a * b),
+                '/' => value.divide(rhs)?,
+                _ => value.remainder(rhs)?,
+            };
+        }
+        Ok(value)
+    }
+
+    fn unary(&mut self) -> Result<Number, String> {
+        match self.peek() {
+            Some(Token::Operator('-')) => {
+                self.position += 1;
+                Ok(self.unary()?.negate())
+            }
+            Some(Token::Operator('+')) => {
+                self.position += 1;
+                self.unary()
+            }
+            _ => self.power(),
+        }
+    }
+
+    fn power(&mut self) -> Result<Number, String> {
+        let base = self.primary()?;
+        if self.peek() != Some(&Token::Operator('^')) {
+            return Ok(base);
+        }
+        self.position += 1;
+        let exponent = self.unary()?;
+        self.operations += 1;
+        Ok(base.power(exponent))
+    }
+
+    fn primary(&mut self) -> Result<Number, String> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(n),
+            Some(Token::Open) => {
+                let value = self.expression()?;
+                self.expect(Token::Close)?;
+                Ok(value)
+            }
+            Some(Token::Name(name)) if self.peek() == Some(&Token::Open) => {
+                self.position += 1;
+                let mut arguments = vec![self.expression()?];
+                while self.peek() == Some(&Token::Comma) {
+                    self.position += 1;
+                    arguments.push(self.expression()?);
+                }
+                self.expect(Token::Close)?;
+                self.operations += 1;
+                call(&name, &arguments)
+            }
+            Some(Token::Name(name)) => match name.as_str() {
+                "pi" => Ok(Number::Float(std::f64::consts::PI)),
+                "e" => Ok(Number::Float(std::f64::consts::E)),
+                _ => Err(format!("unknown constant {:?}", name)),
+            },
+            Some(token) => Err(format!("unexpected {:?}", token)),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+}
+
+/// Apply a function by name. Trigonometry is in radians, `log` is base 10
+/// unless given a base, and `ln` is the natural logarithm.
+fn call(name: &str, arguments: &[Number]) -> Result<Number, String> {
+    let float = |f: fn(f64) -> f64| match arguments {
+        [x] => Ok(Number::Float(f(x.float()))),
+        _ => Err(format!("{} takes one argument", name)),
+    };
+    // Rounding keeps integers exact
+    let round = |f: fn(f64) -> f64| match arguments {
+        [Number::Int(n)] => Ok(Number::Int(*n)),
+        _ => float(f),
+    };
+
+    match name {
+        "sqrt" => float(f64::sqrt),
+        "cbrt" => float(f64::cbrt),
+        "sin" => float(f64::sin),
+        "cos" => float(f64::cos),
+        "tan" => float(f64::tan),
+        "asin" => float(f64::asin),
+        "acos" => float(f64::acos),
+        "atan" => float(f64::atan),
+        "exp" => float(f64::exp),
+        "ln" => float(f64::ln),
+        "log2" => float(f64::log2),
+        "log" => match arguments {
+            [x, base] => Ok(Number::Float(x.float().log(base.float()))),
+            _ => float(f64::log10),
+        },
+        "abs" => match arguments {
+            [Number::Int(n)] => Ok(n
+                .checked_abs()
+                .map_or(Number::Float((*n as f64).abs()), Number::Int)),
+            _ => float(f64::abs),
+        },
+        "floor" => round(f64::floor),
+        "ceil" => round(f64::ceil),
+        "round" => round(f64::round),
+        "pow" => match arguments {
+            [base, exponent] => Ok(base.power(*exponent)),
+            _ => Err("pow takes two arguments".to_string()),
+        },
+        "min" | "max" if !arguments.is_empty() => {
+            let pick = if name == "min" { f64::lt } else { f64::gt };
+            let mut best = arguments[0];
+            for &argument in &arguments[1..] {
+                if pick(&argument.float(), &best.float()) {
+                    best = argument;
+                }
+            }
+            Ok(best)
+        }
+        _ => Err(format!("unknown function {:?}", name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(expression: &str) -> String {
+        evaluate(expression).unwrap().0.to_string()
+    }
+
+    #[test]
+    fn test_evaluate() {
+        // Precedence, associativity and parentheses
+        assert_eq!(eval("2*(3+4)/7"), "2");
+        assert_eq!(eval("1 + 2 * 3"), "7");
+        assert_eq!(eval("10 - 4 - 3"), "3");
+        assert_eq!(eval("-2^2"), "-4");
+        assert_eq!(eval("2^3^2"), "512");
+        assert_eq!(eval("2**-1"), "0.5");
+        assert_eq!(eval("17 % 5"), "2");
+
+        // Integers stay exact, fractions and overflow become floats
+        assert_eq!(eval("7/2"), "3.5");
+        assert_eq!(eval("2^62 * 4"), "18446744073700000000");
+        assert_eq!(eval("(-2)^63/-1"), "9223372036850000000");
+        assert_eq!(eval("0.1 + 0.2"), "0.3");
+        assert_eq!(eval("1.5e3"), "1500");
+
+        // Literals in other bases
+        assert_eq!(eval("0xff + 0b101 + 0o17"), "275");
+
+        // Functions and constants
+        assert_eq!(eval("sqrt(16)"), "4");
+        assert_eq!(eval("sin(pi/2)"), "1");
+        assert_eq!(eval("log(1000)"), "3");
+        assert_eq!(eval("log(8, 2)"), "3");
+        assert_eq!(eval("pow(2, 10)"), "1024");
+        assert_eq!(eval("max(3, -1, 7.5)"), "7.5");
+        assert_eq!(eval("abs(-3) + floor(2.7)"), "5");
+
+        for invalid in ["1/0", "sqrt(-1)", "2 +", "(1", "foo(2)", "1 2", "3 $ 4"] {
+            assert!(evaluate(invalid).is_err(), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_calculator_provider() {
+        let mut provider = CalculatorProvider;
+
+        let items = provider.query("=2*(3+4)/7", 7);
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].title, "2");
+        assert_eq!(items[0].action, Action::Copy("2".to_string()));
+        assert_eq!(provider.query("1024*3", 7)[0].title, "3072");
+
+        // Without "=", only expressions that compute something are evaluated
+        assert_eq!(provider.query("=42", 7)[0].title, "42");
+        assert!(provider.query("42", 7).is_empty());
+        assert!(provider.query("pi", 7).is_empty());
+        assert!(provider.query("firefox", 7).is_empty());
+        assert!(provider.query("=", 7).is_empty());
+    }
+}
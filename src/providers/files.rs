@@ -0,0 +1,135 @@
+use super::{Action, Provider, ResultItem};
+use crate::config::FilesConfig;
+use crate::daemon;
+use crate::files::FileMatch;
+use crate::logs;
+use std::path::Path;
+
+/// Files and folders below the configured roots, searched instead of
+/// everything else when the query starts with a prefix like `/` or `f `.
+/// The daemon keeps the index; asking it blocks, so the UI runs this provider
+/// off its own thread.
+#[derive(Debug, Clone)]
+pub struct FileProvider {
+    prefixes: Vec<String>,
+}
+
+impl FileProvider {
+    /// Without roots nothing is indexed, so no query is taken as a file search
+    pub fn new(config: &FilesConfig) -> Self {
+        if config.roots.is_empty() {
+            return Self {
+                prefixes: Vec::new(),
+            };
+        }
+
+        Self {
+            prefixes: config
+                .prefixes
+                .iter()
+                .filter(|prefix| !prefix.is_empty())
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// What to search for if `query` starts with a file search prefix
+    pub fn file_query<'a>(&self, query: &'a str) -> Option<&'a str> {
+        self.prefixes
+            .iter()
+            .find_map(|prefix| query.strip_prefix(prefix.as_str()))
+            .map(str::trim)
+    }
+}
+
+impl Provider for FileProvider {
+    fn query(&mut self, query: &str, limit: usize) -> Vec<ResultItem> {
+        let Some(query) = self.file_query(query).filter(|query| !query.is_empty()) else {
+            return Vec::new();
+        };
+
+        match daemon::search_files(query, limit) {
+            Ok(files) => files.into_iter().map(file_item).collect(),
+            Err(e) => {
+                logs::log_error(&format!("File search failed: {}", e));
+                Vec::new()
+            }
+        }
+    }
+}
+
+/// A file's name as the title, with a trailing `/` for folders, and the folder
+/// it is in as the subtitle
+fn file_item(file: FileMatch) -> ResultItem {
+    let name = file.path.file_name().map_or_else(
+        || file.path.display().to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    );
+
+    ResultItem {
+        title: if file.is_dir {
+            format!("{}/", name)
+        } else {
+            name
+        },
+        title_matches: file.name_matches,
+        subtitle: file
+            .path
+            .parent()
+            .map(|parent| vec![(display_dir(parent), Vec::new())])
+            .unwrap_or_default(),
+        icon_path: None,
+        score: file.score,
+        approximate: false,
+        action: Action::File(file.path),
+    }
+}
+
+/// Folder path with the home directory shortened to `~`
+fn display_dir(dir: &Path) -> String {
+    match dirs::home_dir().and_then(|home| dir.strip_prefix(home).ok().map(Path::to_path_buf)) {
+        Some(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+        Some(rest) => Path::new("~").join(rest).display().to_string(),
+        None => dir.display().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_file_query() {
+        // File search is off until a root is configured
+        let provider = FileProvider::new(&FilesConfig::default());
+        assert_eq!(provider.file_query("/report"), None);
+
+        let provider = FileProvider::new(&FilesConfig {
+            roots: vec![PathBuf::from("~")],
+            ..FilesConfig::default()
+        });
+        assert_eq!(provider.file_query("/report"), Some("report"));
+        assert_eq!(provider.file_query("f  tax 2025 "), Some("tax 2025"));
+        assert_eq!(provider.file_query("/"), Some(""));
+        assert_eq!(provider.file_query("firefox"), None);
+        assert_eq!(provider.file_query("f"), None);
+
+        let item = file_item(FileMatch {
+            path: PathBuf::from("/srv/projects/launchdock"),
+            is_dir: true,
+            score: 1000.0,
+            name_matches: vec![0, 1],
+        });
+        assert_eq!(item.title, "launchdock/");
+        assert_eq!(item.title_matches, vec![0, 1]);
+        assert_eq!(
+            item.subtitle,
+            vec![("/srv/projects".to_string(), Vec::new())]
+        );
+        assert_eq!(
+            item.action,
+            Action::File(PathBuf::from("/srv/projects/launchdock"))
+        );
+    }
+}
@@ -0,0 +1,364 @@
+use super::{Action, Provider, ResultItem};
+use crate::config::{self, Config};
+use crate::logs;
+use crate::search::{Highlight, SearchFields, SearchIndex};
+use serde::Deserialize;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often a running script is checked for having exited
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Executable from the `providers` config directory, run once per query.
+///
+/// The script reads `{"query": "...", "limit": 7}` on stdin and prints a JSON
+/// list of items, each with a `title`, optional `subtitle`, `icon` path and
+/// `score`, and an `action`: `{"exec": [argv...]}`, `{"open": "uri"}` or
+/// `{"copy": "text"}`. Anything it writes to stderr goes to the log.
+#[derive(Debug, Clone)]
+pub struct Script {
+    path: PathBuf,
+    timeout: Duration,
+    strip_diacritics: bool,
+}
+
+/// Item as printed by a script
+#[derive(Debug, Deserialize)]
+struct ScriptItem {
+    title: String,
+    #[serde(default)]
+    subtitle: Option<String>,
+    #[serde(default)]
+    icon: Option<PathBuf>,
+    /// Relevance on the scale of application match scores; without it the
+    /// item is scored by how well its title and subtitle match the query
+    #[serde(default)]
+    score: Option<f32>,
+    action: ScriptAction,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ScriptAction {
+    Exec(Vec<String>),
+    Open(String),
+    Copy(String),
+}
+
+impl Script {
+    /// Executables in the providers directory, in file name order
+    pub fn discover(config: &Config) -> Vec<Script> {
+        let Some(dir) = config::config_dir().map(|dir| dir.join("providers")) else {
+            return Vec::new();
+        };
+
+        scripts_in(&dir)
+            .into_iter()
+            .map(|path| Script {
+                path,
+                timeout: Duration::from_millis(config.providers.timeout_ms),
+                strip_diacritics: config.search.strip_diacritics,
+            })
+            .collect()
+    }
+
+    fn name(&self) -> String {
+        self.path.file_name().map_or_else(
+            || self.path.display().to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        )
+    }
+
+    /// Items the script prints for `query`, like `query` of the `Provider`
+    /// trait, except that the script is killed as soon as `cancelled` returns
+    /// true, e.g. because a newer query replaced this one
+    pub fn query_unless(
+        &self,
+        query: &str,
+        limit: usize,
+        cancelled: &dyn Fn() -> bool,
+    ) -> Vec<ResultItem> {
+        if query.is_empty() {
+            return Vec::new();
+        }
+
+        let result = self
+            .run(query, limit, cancelled)
+            .and_then(|output| parse_items(&output, query, limit, self.strip_diacritics));
+        result.unwrap_or_else(|e| {
+            if !cancelled() {
+                logs::log_error(&format!("Provider {} failed: {}", self.name(), e));
+            }
+            Vec::new()
+        })
+    }
+
+    /// Run the script for `query`, waiting for its output until the timeout
+    fn run(
+        &self,
+        query: &str,
+        limit: usize,
+        cancelled: &dyn Fn() -> bool,
+    ) -> Result<Vec<u8>, String> {
+        let mut child = Command::new(&self.path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| e.to_string())?;
+
+        // Small enough to fit the pipe buffer, so this never blocks; a script
+        // that ignores its input may already have closed it
+        let request = serde_json::json!({ "query": query, "limit": limit });
+        if let Some(mut stdin) = child.stdin.take() {
+            let _ = stdin.write_all(request.to_string().as_bytes());
+        }
+
+        let mut stdout = child.stdout.take().ok_or("stdout not captured")?;
+        let stderr = child.stderr.take().ok_or("stderr not captured")?;
+        let output = thread::spawn(move || {
+            let mut output = Vec::new();
+            stdout.read_to_end(&mut output).map(|_| output)
+        });
+        let name = self.name();
+        thread::spawn(move || {
+            for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                logs::log_info(&format!("Provider {}: {}", name, line));
+            }
+        });
+
+        let deadline = Instant::now() + self.timeout;
+        let status = loop {
+            match child.try_wait().map_err(|e| e.to_string())? {
+                Some(status) => break status,
+                None if Instant::now() >= deadline => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(format!("timed out after {:?}", self.timeout));
+                }
+                None if cancelled() => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err("cancelled".to_string());
+                }
+                None => thread::sleep(POLL_INTERVAL),
+            }
+        };
+        if !status.success() {
+            return Err(format!("exited with {}", status));
+        }
+
+        output
+            .join()
+            .map_err(|_| "failed to read output".to_string())?
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl Provider for Script {
+    /// Blocks until the script answers, so the UI runs it off its own thread
+    fn query(&mut self, query: &str, limit: usize) -> Vec<ResultItem> {
+        self.query_unless(query, limit, &|| false)
+    }
+}
+
+/// Executable files directly in `dir` in file name order, skipping hidden ones
+fn scripts_in(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut scripts: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+        .filter(|entry| {
+            entry
+                .metadata()
+                .is_ok_and(|metadata| is_executable(&metadata))
+        })
+        .map(|entry| entry.path())
+        .collect();
+    scripts.sort();
+    scripts
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(metadata: &std::fs::Metadata) -> bool {
+    metadata.is_file()
+}
+
+/// Turn a script's output into result items, scoring those without a score of
+/// their own by how well they match `query`. Items with an invalid action are
+/// skipped.
+fn parse_items(
+    output: &[u8],
+    query: &str,
+    limit: usize,
+    strip_diacritics: bool,
+) -> Result<Vec<ResultItem>, String> {
+    let mut items: Vec<ScriptItem> =
+        serde_json::from_slice(output).map_err(|e| format!("invalid output: {}", e))?;
+    items.truncate(limit);
+
+    let search_index = SearchIndex::new(
+        items.iter().map(|item| SearchFields {
+            name: &item.title,
+            generic_name: None,
+            keywords: &[],
+            description: item.subtitle.as_deref(),
+            aliases: &[],
+        }),
+        strip_diacritics,
+    );
+    let tokens = search_index.tokens(query);
+    let mut scores = vec![None; items.len()];
+    for (index, score) in search_index.matches(&tokens, None) {
+        scores[index] = Some(score);
+    }
+
+    let results = items
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, item)| {
+            let action = match item.action {
+                ScriptAction::Exec(mut argv) if !argv.is_empty() => Action::Run {
+                    program: PathBuf::from(argv.remove(0)),
+                    args: argv,
+                },
+                ScriptAction::Exec(_) => return None,
+                ScriptAction::Open(uri) => Action::Open(uri),
+                ScriptAction::Copy(text) => Action::Copy(text),
+            };
+            let highlight = match scores[i] {
+                Some(_) => search_index.highlight(i, &tokens),
+                None => Highlight::default(),
+            };
+            // Unmatched subtitles are still shown, just without highlights
+            let subtitle = match item.subtitle {
+                Some(_) if !highlight.subtitle.is_empty() => highlight.subtitle,
+                Some(subtitle) => vec![(subtitle, Vec::new())],
+                None => Vec::new(),
+            };
+
+            Some(ResultItem {
+                title: item.title,
+                title_matches: highlight.name,
+                subtitle,
+                icon_path: item.icon,
+                score: item.score.or(scores[i]).unwrap_or(0.0),
+                approximate: false,
+                action,
+            })
+        })
+        .collect();
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_items() {
+        let output = br#"[
+            {"title": "PROJ-42", "subtitle": "Fix deploy script", "action": {"open": "https://jira.example.com/browse/PROJ-42"}},
+            {"title": "Deploy runbook", "icon": "/tmp/book.png", "action": {"exec": ["xdg-open", "/srv/runbooks/deploy.md"]}},
+            {"title": "prod", "score": 5000, "action": {"copy": "kubectl config use-context prod"}},
+            {"title": "Broken", "action": {"exec": []}}
+        ]"#;
+
+        let items = parse_items(output, "deploy", 10, true).unwrap();
+        assert_eq!(items.len(), 3);
+
+        // A subtitle match scores lower than a title match, and is highlighted
+        assert_eq!(
+            items[0].subtitle,
+            vec![("Fix deploy script".to_string(), vec![4, 5, 6, 7, 8, 9])]
+        );
+        assert_eq!(
+            items[0].action,
+            Action::Open("https://jira.example.com/browse/PROJ-42".to_string())
+        );
+        assert_eq!(items[1].title_matches, vec![0, 1, 2, 3, 4, 5]);
+        assert!(items[1].score > items[0].score && items[0].score > 0.0);
+        assert_eq!(items[1].icon_path, Some(PathBuf::from("/tmp/book.png")));
+        assert_eq!(
+            items[1].action,
+            Action::Run {
+                program: PathBuf::from("xdg-open"),
+                args: vec!["/srv/runbooks/deploy.md".to_string()],
+            }
+        );
+
+        // A script's own score is kept even if the title doesn't match
+        assert_eq!(items[2].score, 5000.0);
+
+        assert_eq!(parse_items(output, "deploy", 1, true).unwrap().len(), 1);
+        assert!(parse_items(b"{\"title\": 1}", "deploy", 10, true).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_script() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("launchdock-providers-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let write_script = |name: &str, body: &str| {
+            let path = dir.join(name);
+            std::fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+            path
+        };
+        let script = |path: PathBuf| Script {
+            path,
+            timeout: Duration::from_millis(500),
+            strip_diacritics: true,
+        };
+
+        // The request arrives on stdin; here it is echoed back as the item to copy
+        let echo = write_script(
+            "echo",
+            r#"request=$(cat)
+printf '[{"title": "Echo", "action": {"copy": %s}}]' "$(printf '%s' "$request" | sed 's/"/\\"/g; s/.*/"&"/')""#,
+        );
+        let slow = write_script("slow", "sleep 5\necho '[]'");
+        std::fs::write(dir.join("notes.txt"), "not a script").unwrap();
+
+        assert_eq!(scripts_in(&dir).len(), 2);
+
+        let items = script(echo).query("ech", 7);
+        assert_eq!(items.len(), 1);
+        assert_eq!(
+            items[0].action,
+            Action::Copy(r#"{"limit":7,"query":"ech"}"#.to_string())
+        );
+
+        // A script taking too long is killed and adds nothing
+        let start = Instant::now();
+        assert!(script(slow.clone()).query("anything", 7).is_empty());
+        assert!(start.elapsed() < Duration::from_secs(3));
+
+        // ...and so is one whose query was replaced
+        let start = Instant::now();
+        let cancelled = || start.elapsed() > Duration::from_millis(50);
+        assert!(
+            script(slow)
+                .query_unless("anything", 7, &cancelled)
+                .is_empty()
+        );
+        assert!(start.elapsed() < Duration::from_millis(400));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
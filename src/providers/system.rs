@@ -0,0 +1,93 @@
+use super::{Action, Provider, ResultItem};
+use crate::apps::SystemCommand;
+use crate::search::{SearchFields, SearchIndex};
+
+/// Built-in commands of the operating system, e.g. "Shutdown" or "Lock Screen"
+pub struct SystemProvider {
+    commands: Vec<SystemCommand>,
+    /// Command names folded for matching, in the same order
+    index: SearchIndex,
+}
+
+impl SystemProvider {
+    pub fn new(commands: Vec<SystemCommand>, strip_diacritics: bool) -> Self {
+        let index = SearchIndex::new(
+            commands.iter().map(|command| SearchFields {
+                name: &command.name,
+                generic_name: None,
+                keywords: &[],
+                description: None,
+                aliases: &[],
+            }),
+            strip_diacritics,
+        );
+
+        Self { commands, index }
+    }
+}
+
+impl Provider for SystemProvider {
+    fn query(&mut self, query: &str, limit: usize) -> Vec<ResultItem> {
+        let tokens = self.index.tokens(query);
+        let mut matches = self.index.matches(&tokens, None);
+        matches.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        matches
+            .into_iter()
+            .take(limit)
+            .map(|(index, score)| {
+                let command = &self.commands[index];
+                ResultItem {
+                    title: command.name.clone(),
+                    title_matches: self.index.highlight(index, &tokens).name,
+                    subtitle: Vec::new(),
+                    icon_path: command.icon_path.clone(),
+                    score,
+                    approximate: false,
+                    action: Action::Run {
+                        program: command.program.clone(),
+                        args: command.args.clone(),
+                    },
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_system_commands() {
+        let command = |name: &str, program: &str, args: &[&str]| SystemCommand {
+            name: name.to_string(),
+            program: PathBuf::from(program),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            icon_path: None,
+        };
+        let mut provider = SystemProvider::new(
+            vec![
+                command("Shutdown", "systemctl", &["poweroff"]),
+                command("Lock Screen", "loginctl", &["lock-session"]),
+            ],
+            true,
+        );
+
+        let items = provider.query("lock", 7);
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].title, "Lock Screen");
+        assert_eq!(items[0].title_matches, vec![0, 1, 2, 3]);
+        assert_eq!(
+            items[0].action,
+            Action::Run {
+                program: PathBuf::from("loginctl"),
+                args: vec!["lock-session".to_string()],
+            }
+        );
+
+        // Nothing is listed before a query is typed
+        assert!(provider.query("", 7).is_empty());
+    }
+}
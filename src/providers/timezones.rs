@@ -0,0 +1,255 @@
+use super::{ANSWER_SCORE, Action, Provider, ResultItem, conversion_parts};
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::{TZ_VARIANTS, Tz};
+
+/// Converts a time of day between time zones, e.g. `14:00 PST in Berlin`,
+/// `9pm in Tokyo` (from local time) or `now in New York`, using the time
+/// zone database built into the binary. Enter copies the result.
+pub struct TimeZoneProvider;
+
+impl Provider for TimeZoneProvider {
+    fn query(&mut self, query: &str, limit: usize) -> Vec<ResultItem> {
+        if limit == 0 {
+            return Vec::new();
+        }
+
+        let now = Utc::now();
+        conversion_parts(query)
+            .into_iter()
+            .find_map(|(time, target)| convert(&time, &target, now))
+            .map(|(result, date, zone)| ResultItem {
+                title: result.clone(),
+                title_matches: Vec::new(),
+                subtitle: vec![(date, Vec::new()), (zone, Vec::new())],
+                icon_path: None,
+                score: ANSWER_SCORE,
+                approximate: false,
+                action: Action::Copy(result),
+            })
+            .into_iter()
+            .collect()
+    }
+}
+
+/// Common abbreviations, standing for their region's time: `PST` in summer
+/// is read as Pacific Daylight Time, as that's what people usually mean
+const ABBREVIATIONS: &[(&str, &str)] = &[
+    ("UTC", "UTC"),
+    ("Z", "UTC"),
+    ("GMT", "Etc/GMT"),
+    ("PST", "America/Los_Angeles"),
+    ("PDT", "America/Los_Angeles"),
+    ("PT", "America/Los_Angeles"),
+    ("MST", "America/Denver"),
+    ("MDT", "America/Denver"),
+    ("MT", "America/Denver"),
+    ("CST", "America/Chicago"),
+    ("CDT", "America/Chicago"),
+    ("CT", "America/Chicago"),
+    ("EST", "America/New_York"),
+    ("EDT", "America/New_York"),
+    ("ET", "America/New_York"),
+    ("AKST", "America/Anchorage"),
+    ("HST", "Pacific/Honolulu"),
+    ("BRT", "America/Sao_Paulo"),
+    ("WET", "Europe/Lisbon"),
+    ("BST", "Europe/London"),
+    ("CET", "Europe/Berlin"),
+    ("CEST", "Europe/Berlin"),
+    ("EET", "Europe/Athens"),
+    ("EEST", "Europe/Athens"),
+    ("MSK", "Europe/Moscow"),
+    ("IST", "Asia/Kolkata"),
+    ("PKT", "Asia/Karachi"),
+    ("SGT", "Asia/Singapore"),
+    ("HKT", "Asia/Hong_Kong"),
+    ("JST", "Asia/Tokyo"),
+    ("KST", "Asia/Seoul"),
+    ("AWST", "Australia/Perth"),
+    ("ACST", "Australia/Adelaide"),
+    ("AEST", "Australia/Sydney"),
+    ("AEDT", "Australia/Sydney"),
+    ("NZST", "Pacific/Auckland"),
+    ("NZDT", "Pacific/Auckland"),
+];
+
+/// Large cities that share a zone named after another city
+const CITIES: &[(&str, &str)] = &[
+    ("San Francisco", "America/Los_Angeles"),
+    ("Seattle", "America/Los_Angeles"),
+    ("Austin", "America/Chicago"),
+    ("Dallas", "America/Chicago"),
+    ("Houston", "America/Chicago"),
+    ("Atlanta", "America/New_York"),
+    ("Boston", "America/New_York"),
+    ("Miami", "America/New_York"),
+    ("Washington", "America/New_York"),
+    ("Barcelona", "Europe/Madrid"),
+    ("Frankfurt", "Europe/Berlin"),
+    ("Hamburg", "Europe/Berlin"),
+    ("Munich", "Europe/Berlin"),
+    ("Milan", "Europe/Rome"),
+    ("Beijing", "Asia/Shanghai"),
+    ("Shenzhen", "Asia/Shanghai"),
+    ("Osaka", "Asia/Tokyo"),
+    ("Bangalore", "Asia/Kolkata"),
+    ("Bengaluru", "Asia/Kolkata"),
+    ("Delhi", "Asia/Kolkata"),
+    ("Mumbai", "Asia/Kolkata"),
+];
+
+/// Find a zone by abbreviation, IANA name (`Europe/Berlin`) or city
+/// (`Berlin`, `new york`), ignoring case
+fn find_zone(name: &str) -> Option<Tz> {
+    let name = name.trim();
+    let listed = |table: &[(&str, &'static str)]| {
+        table
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .and_then(|(_, zone)| zone.parse().ok())
+    };
+
+    let wanted = name.replace(' ', "_");
+    listed(ABBREVIATIONS)
+        .or_else(|| listed(CITIES))
+        .or_else(|| {
+            TZ_VARIANTS.iter().copied().find(|zone| {
+                let zone_name = zone.name();
+                let city = zone_name.rsplit('/').next().unwrap_or(zone_name);
+                zone_name.eq_ignore_ascii_case(&wanted) || city.eq_ignore_ascii_case(&wanted)
+            })
+        })
+}
+
+/// Read a time of day: `14:00`, `14:00:30`, `2pm`, `2:30 pm`. A bare hour
+/// needs am or pm, so a plain number isn't taken for a time.
+fn parse_clock(text: &str) -> Option<NaiveTime> {
+    let text = text.to_ascii_lowercase();
+    let (clock, pm) = match (text.strip_suffix("am"), text.strip_suffix("pm")) {
+        (Some(clock), _) => (clock.trim(), Some(false)),
+        (_, Some(clock)) => (clock.trim(), Some(true)),
+        _ => (text.as_str(), None),
+    };
+
+    let mut parts = clock.split(':');
+    let hour: u32 = parts.next()?.parse().ok()?;
+    let minute: u32 = match parts.next() {
+        Some(minute) => minute.parse().ok()?,
+        None if pm.is_some() => 0,
+        None => return None,
+    };
+    let second: u32 = match parts.next() {
+        Some(second) => second.parse().ok()?,
+        None => 0,
+    };
+    if parts.next().is_some() {
+        return None;
+    }
+
+    let hour = match pm {
+        Some(_) if !(1..=12).contains(&hour) => return None,
+        Some(pm) => hour % 12 + if pm { 12 } else { 0 },
+        None => hour,
+    };
+    NaiveTime::from_hms_opt(hour, minute, second)
+}
+
+/// Read `source` as a time (or `now`) optionally followed by its zone, and
+/// convert it to the zone named by `target`, on the current date of the
+/// source zone. Returns the converted time with its zone abbreviation, its
+/// date and the zone's full name.
+fn convert(source: &str, target: &str, now: DateTime<Utc>) -> Option<(String, String, String)> {
+    let target = find_zone(target)?;
+
+    let words: Vec<&str> = source.split_whitespace().collect();
+    let (time, zone_words) = match words.as_slice() {
+        [first, rest @ ..] if first.eq_ignore_ascii_case("now") => (None, rest),
+        [clock, meridiem, rest @ ..]
+            if meridiem.eq_ignore_ascii_case("am") || meridiem.eq_ignore_ascii_case("pm") =>
+        {
+            (Some(parse_clock(&format!("{}{}", clock, meridiem))?), rest)
+        }
+        [clock, rest @ ..] => (Some(parse_clock(clock)?), rest),
+        [] => return None,
+    };
+    // Without a zone, the time is local
+    let zone = match zone_words {
+        [] => None,
+        words => Some(find_zone(&words.join(" "))?),
+    };
+
+    let instant = match (time, zone) {
+        (None, _) => now,
+        (Some(time), Some(zone)) => at_time(&zone, now.with_timezone(&zone).date_naive(), time)?,
+        (Some(time), None) => at_time(&Local, now.with_timezone(&Local).date_naive(), time)?,
+    };
+
+    let converted = instant.with_timezone(&target);
+    Some((
+        converted.format("%H:%M %Z").to_string(),
+        converted.format("%a %-d %b").to_string(),
+        target.name().to_string(),
+    ))
+}
+
+/// The instant a zone's clocks show `time` on `date`; the earlier one if
+/// they show it twice as daylight saving time ends
+fn at_time<Z: TimeZone>(zone: &Z, date: NaiveDate, time: NaiveTime) -> Option<DateTime<Utc>> {
+    zone.from_local_datetime(&date.and_time(time))
+        .earliest()
+        .map(|instant| instant.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_clock() {
+        let time = |h, m| NaiveTime::from_hms_opt(h, m, 0);
+        assert_eq!(parse_clock("14:00"), time(14, 0));
+        assert_eq!(parse_clock("9:30PM"), time(21, 30));
+        assert_eq!(parse_clock("12am"), time(0, 0));
+        assert_eq!(parse_clock("12pm"), time(12, 0));
+        assert_eq!(parse_clock("14"), None);
+        assert_eq!(parse_clock("13pm"), None);
+        assert_eq!(parse_clock("25:00"), None);
+    }
+
+    #[test]
+    fn test_convert() {
+        let winter = Utc.with_ymd_and_hms(2026, 1, 15, 12, 0, 0).unwrap();
+        let summer = Utc.with_ymd_and_hms(2026, 7, 15, 12, 0, 0).unwrap();
+        let title = |source: &str, target: &str, now| convert(source, target, now).map(|c| c.0);
+
+        assert_eq!(
+            convert("14:00 PST", "Berlin", winter),
+            Some((
+                "23:00 CET".to_string(),
+                "Thu 15 Jan".to_string(),
+                "Europe/Berlin".to_string()
+            ))
+        );
+        // Daylight saving time applies on both sides in summer
+        assert_eq!(
+            title("14:00 PST", "berlin", summer).as_deref(),
+            Some("23:00 CEST")
+        );
+        assert_eq!(
+            title("9 pm JST", "New York", winter).as_deref(),
+            Some("07:00 EST")
+        );
+        assert!(convert("6:30am", "UTC", winter).is_some()); // From local time
+        assert_eq!(
+            title("now", "Asia/Kolkata", winter).as_deref(),
+            Some("17:30 IST")
+        );
+
+        // Crossing midnight shows the other date
+        let (time, date, _) = convert("23:30 UTC", "Tokyo", winter).unwrap();
+        assert_eq!((time.as_str(), date.as_str()), ("08:30 JST", "Fri 16 Jan"));
+
+        assert_eq!(convert("14:00 PST", "Atlantis", winter), None);
+        assert_eq!(convert("10 km", "mi", winter), None);
+    }
+}
@@ -0,0 +1,333 @@
+use super::{ANSWER_SCORE, Action, Provider, ResultItem, conversion_parts};
+
+/// Converts quantities between units of the same kind, e.g. `10 km in mi`,
+/// `72F to C` or `3 GiB in MB`. Enter copies the result.
+pub struct UnitProvider;
+
+impl Provider for UnitProvider {
+    fn query(&mut self, query: &str, limit: usize) -> Vec<ResultItem> {
+        if limit == 0 {
+            return Vec::new();
+        }
+
+        let Some((value, from, to)) = conversion_parts(query)
+            .into_iter()
+            .find_map(|(quantity, target)| parse_conversion(&quantity, &target))
+        else {
+            return Vec::new();
+        };
+
+        let result = format!(
+            "{} {}",
+            format_number(convert(value, from, to)),
+            to.symbol()
+        );
+        vec![ResultItem {
+            title: result.clone(),
+            title_matches: Vec::new(),
+            subtitle: vec![(
+                format!("{} {}", format_number(value), from.symbol()),
+                Vec::new(),
+            )],
+            icon_path: None,
+            score: ANSWER_SCORE,
+            approximate: false,
+            action: Action::Copy(result),
+        }]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Dimension {
+    Length,
+    Area,
+    Volume,
+    Mass,
+    Temperature,
+    Speed,
+    Duration,
+    Data,
+}
+
+/// Unit whose values convert to the base unit of its dimension as
+/// `value * factor + offset`
+#[derive(Debug, PartialEq)]
+struct Unit {
+    /// Symbol shown in results first, then other accepted names
+    names: &'static [&'static str],
+    dimension: Dimension,
+    factor: f64,
+    offset: f64,
+}
+
+impl Unit {
+    fn symbol(&self) -> &'static str {
+        self.names[0]
+    }
+}
+
+const fn unit(names: &'static [&'static str], dimension: Dimension, factor: f64) -> Unit {
+    Unit {
+        names,
+        dimension,
+        factor,
+        offset: 0.0,
+    }
+}
+
+use Dimension::*;
+
+/// Known units; base units are the meter, square meter, liter, kilogram,
+/// kelvin, meter per second, second and byte. Where names only differ in
+/// case, like MB and Mb, the unit listed first wins for other spellings.
+const UNITS: &[Unit] = &[
+    unit(
+        &[
+            "mm",
+            "millimeter",
+            "millimeters",
+            "millimetre",
+            "millimetres",
+        ],
+        Length,
+        0.001,
+    ),
+    unit(
+        &[
+            "cm",
+            "centimeter",
+            "centimeters",
+            "centimetre",
+            "centimetres",
+        ],
+        Length,
+        0.01,
+    ),
+    unit(&["m", "meter", "meters", "metre", "metres"], Length, 1.0),
+    unit(
+        &["km", "kilometer", "kilometers", "kilometre", "kilometres"],
+        Length,
+        1000.0,
+    ),
+    unit(&["in", "inch", "inches", "\""], Length, 0.0254),
+    unit(&["ft", "foot", "feet", "'"], Length, 0.3048),
+    unit(&["yd", "yard", "yards"], Length, 0.9144),
+    unit(&["mi", "mile", "miles"], Length, 1609.344),
+    unit(&["nmi", "nautical mile", "nautical miles"], Length, 1852.0),
+    unit(
+        &["m²", "m2", "sqm", "square meter", "square meters"],
+        Area,
+        1.0,
+    ),
+    unit(
+        &["km²", "km2", "square kilometer", "square kilometers"],
+        Area,
+        1e6,
+    ),
+    unit(
+        &["ft²", "ft2", "sqft", "square foot", "square feet"],
+        Area,
+        0.09290304,
+    ),
+    unit(&["ha", "hectare", "hectares"], Area, 1e4),
+    unit(&["acre", "acres", "ac"], Area, 4046.8564224),
+    unit(
+        &["mi²", "mi2", "square mile", "square miles"],
+        Area,
+        2_589_988.110336,
+    ),
+    unit(
+        &[
+            "ml",
+            "milliliter",
+            "milliliters",
+            "millilitre",
+            "millilitres",
+        ],
+        Volume,
+        0.001,
+    ),
+    unit(
+        &[
+            "cl",
+            "centiliter",
+            "centiliters",
+            "centilitre",
+            "centilitres",
+        ],
+        Volume,
+        0.01,
+    ),
+    unit(&["l", "liter", "liters", "litre", "litres"], Volume, 1.0),
+    unit(&["m³", "m3", "cubic meter", "cubic meters"], Volume, 1000.0),
+    unit(&["tsp", "teaspoon", "teaspoons"], Volume, 0.00492892159375),
+    unit(
+        &["tbsp", "tablespoon", "tablespoons"],
+        Volume,
+        0.01478676478125,
+    ),
+    unit(
+        &["fl oz", "floz", "fluid ounce", "fluid ounces"],
+        Volume,
+        0.0295735295625,
+    ),
+    unit(&["cup", "cups"], Volume, 0.2365882365),
+    unit(&["pt", "pint", "pints"], Volume, 0.473176473),
+    unit(&["qt", "quart", "quarts"], Volume, 0.946352946),
+    unit(&["gal", "gallon", "gallons"], Volume, 3.785411784),
+    unit(&["mg", "milligram", "milligrams"], Mass, 1e-6),
+    unit(&["g", "gram", "grams"], Mass, 0.001),
+    unit(&["kg", "kilogram", "kilograms", "kilo", "kilos"], Mass, 1.0),
+    unit(&["t", "tonne", "tonnes", "ton", "tons"], Mass, 1000.0),
+    unit(&["oz", "ounce", "ounces"], Mass, 0.028349523125),
+    unit(&["lb", "lbs", "pound", "pounds"], Mass, 0.45359237),
+    unit(&["st", "stone", "stones"], Mass, 6.35029318),
+    Unit {
+        names: &["°C", "C", "celsius"],
+        dimension: Temperature,
+        factor: 1.0,
+        offset: 273.15,
+    },
+    Unit {
+        names: &["°F", "F", "fahrenheit"],
+        dimension: Temperature,
+        factor: 5.0 / 9.0,
+        offset: 273.15 - 32.0 * 5.0 / 9.0,
+    },
+    unit(&["K", "kelvin", "kelvins"], Temperature, 1.0),
+    unit(&["m/s", "mps", "meters per second"], Speed, 1.0),
+    unit(
+        &["km/h", "kmh", "kph", "kilometers per hour"],
+        Speed,
+        1.0 / 3.6,
+    ),
+    unit(&["mph", "mi/h", "miles per hour"], Speed, 0.44704),
+    unit(&["kn", "knot", "knots", "kt"], Speed, 1852.0 / 3600.0),
+    unit(&["ms", "millisecond", "milliseconds"], Duration, 0.001),
+    unit(&["s", "sec", "secs", "second", "seconds"], Duration, 1.0),
+    unit(&["min", "mins", "minute", "minutes"], Duration, 60.0),
+    unit(&["h", "hr", "hrs", "hour", "hours"], Duration, 3600.0),
+    unit(&["d", "day", "days"], Duration, 86400.0),
+    unit(&["wk", "week", "weeks"], Duration, 604_800.0),
+    unit(&["yr", "year", "years"], Duration, 31_557_600.0),
+    unit(&["B", "byte", "bytes"], Data, 1.0),
+    unit(&["kB", "KB", "kilobyte", "kilobytes"], Data, 1e3),
+    unit(&["MB", "megabyte", "megabytes"], Data, 1e6),
+    unit(&["GB", "gigabyte", "gigabytes"], Data, 1e9),
+    unit(&["TB", "terabyte", "terabytes"], Data, 1e12),
+    unit(&["PB", "petabyte", "petabytes"], Data, 1e15),
+    unit(&["KiB", "kibibyte", "kibibytes"], Data, 1024.0),
+    unit(&["MiB", "mebibyte", "mebibytes"], Data, 1_048_576.0),
+    unit(&["GiB", "gibibyte", "gibibytes"], Data, 1_073_741_824.0),
+    unit(&["TiB", "tebibyte", "tebibytes"], Data, 1_099_511_627_776.0),
+    unit(
+        &["PiB", "pebibyte", "pebibytes"],
+        Data,
+        1_125_899_906_842_624.0,
+    ),
+    unit(&["bit", "bits", "b"], Data, 0.125),
+    unit(&["kbit", "Kb", "kilobit", "kilobits"], Data, 125.0),
+    unit(&["Mbit", "Mb", "megabit", "megabits"], Data, 125_000.0),
+    unit(&["Gbit", "Gb", "gigabit", "gigabits"], Data, 1.25e8),
+    unit(&["Tbit", "Tb", "terabit", "terabits"], Data, 1.25e11),
+];
+
+/// Look up a unit by name, preferring an exact match so that "MB" and "Mb"
+/// stay apart. A leading "°" is optional.
+fn find_unit(name: &str) -> Option<&'static Unit> {
+    let name = name.trim();
+    let bare = name.strip_prefix('°').unwrap_or(name);
+    let exact = |name: &str| UNITS.iter().find(|unit| unit.names.contains(&name));
+
+    exact(name).or_else(|| exact(bare)).or_else(|| {
+        UNITS.iter().find(|unit| {
+            unit.names
+                .iter()
+                .any(|unit_name| unit_name.eq_ignore_ascii_case(bare))
+        })
+    })
+}
+
+/// Read `quantity` as a number followed by a unit, with or without a space
+/// between them, and `target` as a unit of the same dimension
+fn parse_conversion(quantity: &str, target: &str) -> Option<(f64, &'static Unit, &'static Unit)> {
+    let quantity = quantity.trim();
+    let number_end = quantity
+        .char_indices()
+        .find(|&(i, c)| !(c.is_ascii_digit() || c == '.' || (i == 0 && matches!(c, '-' | '+'))))
+        .map_or(quantity.len(), |(i, _)| i);
+    let value: f64 = quantity[..number_end].parse().ok()?;
+
+    let from = find_unit(&quantity[number_end..])?;
+    let to = find_unit(target)?;
+    (from.dimension == to.dimension).then_some((value, from, to))
+}
+
+fn convert(value: f64, from: &Unit, to: &Unit) -> f64 {
+    let base = value * from.factor + from.offset;
+    (base - to.offset) / to.factor
+}
+
+/// Six significant digits, but never rounding away digits before the point,
+/// and without trailing zeros
+fn format_number(x: f64) -> String {
+    if x == 0.0 || !x.is_finite() {
+        return x.to_string();
+    }
+
+    let magnitude = x.abs().log10().floor() as i32;
+    let decimals = (5 - magnitude).clamp(0, 15) as usize;
+    let formatted = format!("{:.*}", decimals, x);
+    let formatted = if formatted.contains('.') {
+        formatted.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        &formatted
+    };
+
+    match formatted {
+        "-0" => "0".to_string(),
+        formatted => formatted.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert_query(query: &str) -> Option<String> {
+        UnitProvider
+            .query(query, 7)
+            .into_iter()
+            .next()
+            .map(|item| item.title)
+    }
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(convert_query("10 km in mi").as_deref(), Some("6.21371 mi"));
+        assert_eq!(convert_query("72F to C").as_deref(), Some("22.2222 °C"));
+        assert_eq!(convert_query("-40 °C in °F").as_deref(), Some("-40 °F"));
+        assert_eq!(convert_query("0 K to C").as_deref(), Some("-273.15 °C"));
+        assert_eq!(convert_query("3 GiB in MB").as_deref(), Some("3221.23 MB"));
+        assert_eq!(
+            convert_query("3 GiB in bytes").as_deref(),
+            Some("3221225472 B")
+        );
+        assert_eq!(convert_query("100 Mb in MB").as_deref(), Some("12.5 MB"));
+        assert_eq!(convert_query("1 mm to km").as_deref(), Some("0.000001 km"));
+        assert_eq!(
+            convert_query("5.5 lbs as kg").as_deref(),
+            Some("2.49476 kg")
+        );
+        assert_eq!(convert_query("90 min in h").as_deref(), Some("1.5 h"));
+        assert_eq!(convert_query("2 in in cm").as_deref(), Some("5.08 cm"));
+        assert_eq!(convert_query("1 gal to l").as_deref(), Some("3.78541 l"));
+
+        // Units of different kinds, unknown units and plain searches
+        assert_eq!(convert_query("10 km in kg"), None);
+        assert_eq!(convert_query("10 km in parsecs"), None);
+        assert_eq!(convert_query("km in mi"), None);
+        assert_eq!(convert_query("how to"), None);
+        assert_eq!(convert_query("firefox"), None);
+    }
+}
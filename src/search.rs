@@ -0,0 +1,633 @@
+use caseless::Caseless;
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::is_combining_mark;
+
+/// Searchable text of one catalog entry
+pub struct SearchFields<'a> {
+    pub name: &'a str,
+    pub generic_name: Option<&'a str>,
+    pub keywords: &'a [String],
+    pub description: Option<&'a str>,
+    /// Configured by the user, matched like the name
+    pub aliases: &'a [String],
+}
+
+/// Catalog folded for matching once when it loads, so a keystroke only has to
+/// fold the query. Entries are referred to by their index in the catalog.
+pub struct SearchIndex {
+    entries: Vec<IndexedEntry>,
+    strip_diacritics: bool,
+}
+
+impl SearchIndex {
+    pub fn new<'a>(
+        entries: impl IntoIterator<Item = SearchFields<'a>>,
+        strip_diacritics: bool,
+    ) -> Self {
+        let mut index = Self {
+            entries: Vec::new(),
+            strip_diacritics,
+        };
+        for fields in entries {
+            index.push(fields);
+        }
+        index
+    }
+
+    /// Add an entry after the existing ones
+    pub fn push(&mut self, fields: SearchFields) {
+        let fold = |text: &str| FoldedText::new(text, self.strip_diacritics);
+        self.entries.push(IndexedEntry {
+            name: fold(fields.name),
+            generic_name: fields.generic_name.map(fold),
+            keywords: fields
+                .keywords
+                .iter()
+                .map(|keyword| fold(keyword))
+                .collect(),
+            description: fields.description.map(fold),
+            aliases: fields.aliases.iter().map(|alias| fold(alias)).collect(),
+        });
+    }
+
+    /// Keep only the entries whose index `keep` accepts, in their order
+    pub fn retain(&mut self, mut keep: impl FnMut(usize) -> bool) {
+        let mut index = 0;
+        self.entries.retain(|_| {
+            index += 1;
+            keep(index - 1)
+        });
+    }
+
+    /// Words of the query, folded like the catalog
+    pub fn tokens(&self, query: &str) -> Vec<Vec<char>> {
+        query
+            .split_whitespace()
+            .map(|token| FoldedText::new(token, self.strip_diacritics).chars)
+            .filter(|token| !token.is_empty())
+            .collect()
+    }
+
+    /// Entries matching every token, in any order and field, with the sum of
+    /// their token scores. Only `candidates` are scored when given.
+    pub fn matches(&self, tokens: &[Vec<char>], candidates: Option<&[usize]>) -> Vec<(usize, f32)> {
+        if tokens.is_empty() {
+            return Vec::new();
+        }
+
+        let score = |index: usize| {
+            let score: Option<f32> = tokens
+                .iter()
+                .map(|token| {
+                    self.entries[index]
+                        .token_match(token, false)
+                        .map(|m| m.score)
+                })
+                .sum();
+            score.map(|score| (index, score))
+        };
+
+        match candidates {
+            Some(candidates) => candidates.iter().copied().filter_map(score).collect(),
+            None => (0..self.entries.len()).filter_map(score).collect(),
+        }
+    }
+
+    /// Entries not in `exclude` whose name matches every token within its typo
+    /// budget, fewest edits first, then shortest name
+    pub fn approximate(&self, tokens: &[Vec<char>], exclude: &[usize]) -> Vec<usize> {
+        if tokens.iter().all(|token| max_typo_edits(token.len()) == 0) {
+            return Vec::new();
+        }
+
+        let mut matches: Vec<(usize, usize, usize)> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| !exclude.contains(index))
+            .filter_map(|(index, entry)| {
+                let edits: Option<usize> = tokens
+                    .iter()
+                    .map(|token| {
+                        typo_distance(token, &entry.name.chars, max_typo_edits(token.len()))
+                    })
+                    .sum();
+                edits.map(|edits| (index, edits, entry.name.len))
+            })
+            .collect();
+
+        matches.sort_by_key(|&(_, edits, name_len)| (edits, name_len));
+
+        matches.into_iter().map(|(index, _, _)| index).collect()
+    }
+
+    /// Matched characters of every token in the field it matched best
+    pub fn highlight(&self, index: usize, tokens: &[Vec<char>]) -> Highlight {
+        let entry = &self.entries[index];
+        let mut highlight = Highlight::default();
+
+        for token in tokens {
+            let Some(token_match) = entry.token_match(token, true) else {
+                continue;
+            };
+
+            let positions = match token_match.field {
+                Field::Name => &mut highlight.name,
+                field => {
+                    let text = &entry.field(field).text;
+                    let index = highlight
+                        .subtitle
+                        .iter()
+                        .position(|(subtitle, _)| subtitle == text)
+                        .unwrap_or_else(|| {
+                            highlight.subtitle.push((text.clone(), Vec::new()));
+                            highlight.subtitle.len() - 1
+                        });
+                    &mut highlight.subtitle[index].1
+                }
+            };
+            positions.extend(token_match.positions);
+            positions.sort_unstable();
+            positions.dedup();
+        }
+
+        highlight
+    }
+}
+
+/// Whether everything matching `tokens` also matched `previous`, so the new
+/// query only needs to look at the previous matches. True when each previous
+/// token is a prefix of the token in its place, as when typing on.
+pub fn refines(previous: &[Vec<char>], tokens: &[Vec<char>]) -> bool {
+    !previous.is_empty()
+        && tokens.len() >= previous.len()
+        && previous
+            .iter()
+            .zip(tokens)
+            .all(|(previous, token)| token.starts_with(previous))
+}
+
+/// Why a displayed result matched
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Highlight {
+    /// Matched character positions in the name
+    pub name: Vec<usize>,
+    /// Secondary fields that matched, with their matched character positions
+    pub subtitle: Vec<(String, Vec<usize>)>,
+}
+
+// Weights of matches on secondary fields relative to a match on the name
+const GENERIC_NAME_WEIGHT: f32 = 0.7;
+const KEYWORD_WEIGHT: f32 = 0.6;
+const DESCRIPTION_WEIGHT: f32 = 0.4;
+
+/// Added when a query token is a whole alias. Far above any fuzzy score, even
+/// boosted by launch history, so typing an alias always ranks its entry first.
+const EXACT_ALIAS_BONUS: f32 = 100_000.0;
+
+/// Searchable field of an entry
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Name,
+    GenericName,
+    /// Index into the entry's keywords
+    Keyword(usize),
+    Description,
+    /// Index into the entry's aliases
+    Alias(usize),
+}
+
+/// Best match of one query token among an entry's fields
+struct TokenMatch {
+    /// Weighted by the field it matched in
+    score: f32,
+    field: Field,
+    /// Matched character positions in the field's text
+    positions: Vec<usize>,
+}
+
+struct IndexedEntry {
+    name: FoldedText,
+    generic_name: Option<FoldedText>,
+    keywords: Vec<FoldedText>,
+    description: Option<FoldedText>,
+    aliases: Vec<FoldedText>,
+}
+
+impl IndexedEntry {
+    fn field(&self, field: Field) -> &FoldedText {
+        match field {
+            Field::Name => &self.name,
+            Field::GenericName => self.generic_name.as_ref().unwrap(),
+            Field::Keyword(index) => &self.keywords[index],
+            Field::Description => self.description.as_ref().unwrap(),
+            Field::Alias(index) => &self.aliases[index],
+        }
+    }
+
+    /// Best match of one query token against the entry's searchable fields.
+    /// The score of a multi-word query is the sum over its tokens. Fuzzy matches
+    /// only come with positions when asked for, as only highlights use them.
+    fn token_match(&self, token: &[char], positions: bool) -> Option<TokenMatch> {
+        let weighted = |field: Field, weight: f32| {
+            move |(score, positions): (f32, Vec<usize>)| TokenMatch {
+                score: score * weight,
+                field,
+                positions,
+            }
+        };
+
+        let name = fuzzy_match(token, &self.name, positions).map(weighted(Field::Name, 1.0));
+
+        let generic_name = self
+            .generic_name
+            .as_ref()
+            .and_then(|text| fuzzy_match(token, text, positions))
+            .map(weighted(Field::GenericName, GENERIC_NAME_WEIGHT));
+
+        let keyword = self
+            .keywords
+            .iter()
+            .enumerate()
+            .filter_map(|(index, keyword)| {
+                fuzzy_match(token, keyword, positions)
+                    .map(weighted(Field::Keyword(index), KEYWORD_WEIGHT))
+            })
+            .max_by(|a, b| a.score.total_cmp(&b.score));
+
+        // Descriptions are long sentences, so only contiguous matches count
+        let description = self
+            .description
+            .as_ref()
+            .and_then(|text| substring_match(token, text))
+            .map(weighted(Field::Description, DESCRIPTION_WEIGHT));
+
+        let alias = self
+            .aliases
+            .iter()
+            .enumerate()
+            .filter_map(|(index, alias)| {
+                let exact_bonus = if alias.chars == token {
+                    EXACT_ALIAS_BONUS
+                } else {
+                    0.0
+                };
+                fuzzy_match(token, alias, positions)
+                    .map(|(score, positions)| (score + exact_bonus, positions))
+                    .map(weighted(Field::Alias(index), 1.0))
+            })
+            .max_by(|a, b| a.score.total_cmp(&b.score));
+
+        [name, generic_name, keyword, description, alias]
+            .into_iter()
+            .flatten()
+            .max_by(|a, b| a.score.total_cmp(&b.score))
+    }
+}
+
+/// Edits allowed when matching a query of this many characters with typos.
+/// Short queries get none: with one edit, most 4-character queries match something.
+fn max_typo_edits(query_len: usize) -> usize {
+    match query_len {
+        0..=4 => 0,
+        5..=8 => 1,
+        _ => 2,
+    }
+}
+
+/// Fewest edits to the query that make it a subsequence of `text`, if at most
+/// `max_edits`. Characters missing from the query are free, as text characters
+/// can be skipped anyway; an extra or mistyped query character costs one edit,
+/// and so does swapping two adjacent characters ("thunderbrid").
+fn typo_distance(query_chars: &[char], text_chars: &[char], max_edits: usize) -> Option<usize> {
+    let (query_len, text_len) = (query_chars.len(), text_chars.len());
+
+    // edits[i][j]: fewest edits to match query[..i] within text[..j]
+    let mut edits = vec![vec![0; text_len + 1]; query_len + 1];
+    for i in 1..=query_len {
+        edits[i][0] = i;
+        for j in 1..=text_len {
+            let mut fewest = edits[i][j - 1].min(edits[i - 1][j] + 1);
+            if query_chars[i - 1] == text_chars[j - 1] {
+                fewest = fewest.min(edits[i - 1][j - 1]);
+            }
+            if i >= 2
+                && j >= 2
+                && query_chars[i - 1] == text_chars[j - 2]
+                && query_chars[i - 2] == text_chars[j - 1]
+            {
+                fewest = fewest.min(edits[i - 2][j - 2] + 1);
+            }
+            edits[i][j] = fewest;
+        }
+    }
+
+    Some(edits[query_len][text_len]).filter(|&distance| distance <= max_edits)
+}
+
+/// Text prepared for matching: compatibility-normalized and Unicode case folded,
+/// with combining marks removed when diacritics are stripped. Remembers which
+/// original character each folded character came from, e.g. "ß" folds to "ss".
+struct FoldedText {
+    /// The original text
+    text: String,
+    chars: Vec<char>,
+    /// Index of the original character behind each entry of `chars`
+    origins: Vec<usize>,
+    /// Word boundary bonus of each entry of `chars`
+    bonuses: Vec<f32>,
+    /// Number of characters in the original text
+    len: usize,
+}
+
+impl FoldedText {
+    fn new(text: &str, strip_diacritics: bool) -> Self {
+        let mut folded = Self {
+            text: text.to_string(),
+            chars: Vec::with_capacity(text.len()),
+            origins: Vec::with_capacity(text.len()),
+            bonuses: Vec::with_capacity(text.len()),
+            len: 0,
+        };
+
+        let mut prev = None;
+        for (index, c) in text.chars().enumerate() {
+            let start = folded.chars.len();
+            if strip_diacritics {
+                // Decompose so accents become separate marks that can be dropped
+                let chars = std::iter::once(c).nfkd().default_case_fold().nfkd();
+                folded.extend(chars.filter(|&c| !is_combining_mark(c)), index);
+            } else {
+                let chars = std::iter::once(c).nfkc().default_case_fold().nfkc();
+                folded.extend(chars, index);
+            }
+            // Only the first character a boundary folds into starts the word
+            if folded.chars.len() > start {
+                folded.bonuses[start] = boundary_bonus(prev, c);
+            }
+            prev = Some(c);
+            folded.len = index + 1;
+        }
+
+        folded
+    }
+
+    fn extend(&mut self, chars: impl Iterator<Item = char>, origin: usize) {
+        for c in chars {
+            self.chars.push(c);
+            self.origins.push(origin);
+            self.bonuses.push(0.0);
+        }
+    }
+
+    /// Map positions in the folded text back to original character indices
+    fn original_positions(&self, positions: &[usize]) -> Vec<usize> {
+        let mut original: Vec<usize> = positions.iter().map(|&pos| self.origins[pos]).collect();
+        original.dedup();
+        original
+    }
+}
+
+// A match scores a base favoring short texts, a bonus for matching early, the
+// boundary bonus of every matched character, a bonus for each pair of consecutive
+// matched characters, and a penalty for each character skipped between them.
+const CONSECUTIVE_BONUS: f32 = 200.0;
+const GAP_PENALTY: f32 = 15.0;
+
+/// Character starting a word, e.g. "S" in "Visual Studio" or "v" in "gnome-video".
+/// Matching only word starts is what makes acronyms like "vsc" score well.
+const WORD_START_BONUS: f32 = 150.0;
+
+/// Character starting a part of a compound word, e.g. "O" in "LibreOffice" or "2" in "Mp3tag2"
+const WORD_PART_BONUS: f32 = 120.0;
+
+fn boundary_bonus(prev: Option<char>, c: char) -> f32 {
+    match prev {
+        None => WORD_START_BONUS,
+        Some(prev) if !prev.is_alphanumeric() && c.is_alphanumeric() => WORD_START_BONUS,
+        Some(prev) if prev.is_lowercase() && c.is_uppercase() => WORD_PART_BONUS,
+        Some(prev)
+            if prev.is_alphabetic() && c.is_numeric() || prev.is_numeric() && c.is_alphabetic() =>
+        {
+            WORD_PART_BONUS
+        }
+        _ => 0.0,
+    }
+}
+
+/// Base score: prefer shorter texts but give substantial base points
+fn length_bonus(text_len: usize) -> f32 {
+    1000.0 / (text_len as f32).max(1.0)
+}
+
+/// Matches earlier in the text get a small bonus
+fn early_bonus(first_position: usize) -> f32 {
+    50.0 / (first_position as f32 + 1.0)
+}
+
+/// Whether the query characters all occur in `text_chars` in order
+fn is_subsequence(query_chars: &[char], text_chars: &[char]) -> bool {
+    let mut text = text_chars.iter();
+    query_chars.iter().all(|c| text.any(|t| t == c))
+}
+
+/// Best-scoring alignment of the query characters in `text`, in order but not
+/// necessarily contiguous. Returns its score and, when asked for `positions`,
+/// the matched original character positions; ranking only needs the score.
+/// Dynamic programming over (query, text) positions rather than taking the
+/// leftmost occurrences, which for "vsc" would pick the "s" inside "Visual"
+/// over the "S" of "Studio".
+fn fuzzy_match(
+    query_chars: &[char],
+    text: &FoldedText,
+    positions: bool,
+) -> Option<(f32, Vec<usize>)> {
+    let (query_len, text_len) = (query_chars.len(), text.chars.len());
+    // Most entries don't match at all; reject them before allocating the tables
+    if query_len == 0 || query_len > text_len || !is_subsequence(query_chars, &text.chars) {
+        return None;
+    }
+
+    // best[i][j]: score of the best alignment of query[..=i] with query[i] at text[j]
+    // from[i][j]: text position of query[i - 1] in that alignment
+    // Both are stored row by row in a single allocation
+    let mut best = vec![f32::NEG_INFINITY; query_len * text_len];
+    let mut from = vec![0; if positions { query_len * text_len } else { 0 }];
+    let at = |i: usize, j: usize| i * text_len + j;
+
+    for (j, &c) in text.chars.iter().enumerate() {
+        if c == query_chars[0] {
+            best[j] = early_bonus(text.origins[j]) + text.bonuses[j];
+        }
+    }
+
+    for i in 1..query_len {
+        // Best predecessor at least two characters back, before its gap penalty
+        let mut gapped_best = f32::NEG_INFINITY;
+        let mut gapped_from = 0;
+
+        for j in 1..text_len {
+            if j >= 2 {
+                let k = j - 2;
+                let candidate = best[at(i - 1, k)] + GAP_PENALTY * k as f32;
+                if candidate > gapped_best {
+                    gapped_best = candidate;
+                    gapped_from = k;
+                }
+            }
+
+            if text.chars[j] != query_chars[i] {
+                continue;
+            }
+
+            let consecutive = best[at(i - 1, j - 1)] + CONSECUTIVE_BONUS;
+            let gapped = gapped_best - GAP_PENALTY * (j - 1) as f32;
+            let (score, prev) = if consecutive >= gapped {
+                (consecutive, j - 1)
+            } else {
+                (gapped, gapped_from)
+            };
+
+            if score > f32::NEG_INFINITY {
+                best[at(i, j)] = score + text.bonuses[j];
+                if positions {
+                    from[at(i, j)] = prev;
+                }
+            }
+        }
+    }
+
+    // Earliest end position among the best alignments
+    let (mut j, score) = best[at(query_len - 1, 0)..]
+        .iter()
+        .copied()
+        .enumerate()
+        .rev()
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+    if score == f32::NEG_INFINITY {
+        return None; // Not all query characters found in order
+    }
+
+    if !positions {
+        return Some((score + length_bonus(text.len), Vec::new()));
+    }
+    let mut positions = vec![0; query_len];
+    for i in (0..query_len).rev() {
+        positions[i] = j;
+        j = from[at(i, j)];
+    }
+
+    Some((
+        score + length_bonus(text.len),
+        text.original_positions(&positions),
+    ))
+}
+
+/// Best-scoring contiguous occurrence of the query in `text`, with its score and
+/// original character positions
+fn substring_match(query_chars: &[char], text: &FoldedText) -> Option<(f32, Vec<usize>)> {
+    let query_len = query_chars.len();
+    if query_len == 0 {
+        return None;
+    }
+
+    let (start, score) = text
+        .chars
+        .windows(query_len)
+        .enumerate()
+        .filter(|(_, window)| *window == query_chars)
+        .map(|(start, _)| {
+            let bonuses: f32 = text.bonuses[start..start + query_len].iter().sum();
+            let score = early_bonus(text.origins[start])
+                + bonuses
+                + CONSECUTIVE_BONUS * (query_len - 1) as f32;
+            (start, score)
+        })
+        .rev()
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+    let positions: Vec<usize> = (start..start + query_len).collect();
+
+    Some((
+        score + length_bonus(text.len),
+        text.original_positions(&positions),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_folded_positions() {
+        let query = |query: &str| FoldedText::new(query, true).chars;
+        let fuzzy = |query_text: &str, text: &FoldedText| {
+            fuzzy_match(&query(query_text), text, true).map(|(_, positions)| positions)
+        };
+        let substring = |query_text: &str, text: &FoldedText| {
+            substring_match(&query(query_text), text).map(|(_, positions)| positions)
+        };
+
+        // Positions are character indices into the original text
+        let text = FoldedText::new("Größe", true);
+        assert_eq!(fuzzy("grosse", &text), Some(vec![0, 1, 2, 3, 4]));
+        assert_eq!(fuzzy("ge", &text), Some(vec![0, 4]));
+
+        let text = FoldedText::new("Mise à jour", true);
+        assert_eq!(fuzzy("aj", &text), Some(vec![5, 7]));
+        assert_eq!(substring("a jo", &text), Some(vec![5, 6, 7, 8]));
+
+        // A decomposed accent maps back to its base letter
+        let text = FoldedText::new("Cafe\u{301}", true);
+        assert_eq!(text.len, 5);
+        assert_eq!(substring("café", &text), Some(vec![0, 1, 2, 3]));
+    }
+
+    #[test]
+    fn test_optimal_alignment() {
+        let positions = |query: &str, text: &str| {
+            let query = FoldedText::new(query, true).chars;
+            fuzzy_match(&query, &FoldedText::new(text, true), true).map(|(_, positions)| positions)
+        };
+
+        // Word starts win over the leftmost occurrences
+        assert_eq!(positions("vsc", "Visual Studio Code"), Some(vec![0, 7, 14]));
+        assert_eq!(positions("lo", "LibreOffice"), Some(vec![0, 5]));
+        assert_eq!(positions("gv", "gnome-video"), Some(vec![0, 6]));
+        // A consecutive run beats scattered characters
+        assert_eq!(positions("ode", "Code Oxide"), Some(vec![1, 2, 3]));
+        assert_eq!(positions("xyz", "Visual Studio Code"), None);
+
+        let text = FoldedText::new("Visual Studio Code", true);
+        assert_eq!(text.bonuses[0], WORD_START_BONUS);
+        assert_eq!(text.bonuses[7], WORD_START_BONUS);
+        assert_eq!(text.bonuses[2], 0.0);
+        let text = FoldedText::new("KeePassXC 2", true);
+        assert_eq!(text.bonuses[3], WORD_PART_BONUS);
+        assert_eq!(text.bonuses[10], WORD_START_BONUS);
+    }
+
+    #[test]
+    fn test_typo_distance() {
+        assert_eq!(typo_distance(&['a', 'b'], &['b', 'a'], 2), Some(1));
+        assert_eq!(typo_distance(&['a', 'b', 'c'], &['x'], 2), None);
+        assert_eq!(typo_distance(&['a', 'c'], &['a', 'b', 'c'], 0), Some(0));
+    }
+
+    #[test]
+    fn test_refines() {
+        let index = SearchIndex::new([], true);
+        let refines =
+            |previous: &str, query: &str| refines(&index.tokens(previous), &index.tokens(query));
+
+        assert!(refines("fi", "fir"));
+        assert!(refines("fi", "FIR"));
+        assert!(refines("term", "term emu"));
+        assert!(refines("term ", "term"));
+        assert!(refines("te em", "term emu"));
+
+        assert!(!refines("fir", "fi")); // Deleting widens the results
+        assert!(!refines("term emu", "term"));
+        assert!(!refines("fir", "fx"));
+        assert!(!refines("", "f")); // The empty query lists favorites, not matches
+    }
+}
@@ -0,0 +1,147 @@
+use crate::apps::find_executable;
+use crate::config::TerminalConfig;
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// Terminal emulators probed in order when neither the config nor `$TERMINAL` names
+/// one, with the arguments each expects before the command it should run
+const KNOWN_TERMINALS: &[(&str, &[&str])] = &[
+    ("x-terminal-emulator", &["-e"]),
+    ("foot", &[]),
+    ("kitty", &[]),
+    ("alacritty", &["-e"]),
+    ("wezterm", &["start", "--"]),
+    ("ghostty", &["-e"]),
+    ("gnome-terminal", &["--"]),
+    ("kgx", &["--"]),
+    ("konsole", &["-e"]),
+    ("xfce4-terminal", &["-x"]),
+    ("terminator", &["-x"]),
+    ("urxvt", &["-e"]),
+    ("st", &["-e"]),
+    ("xterm", &["-e"]),
+];
+
+/// Rewrite a command so it runs inside a terminal emulator.
+/// Returns `None` when no terminal emulator can be found.
+pub fn wrap_command(
+    config: &TerminalConfig,
+    exe_path: &Path,
+    args: &[String],
+) -> Option<(PathBuf, Vec<String>)> {
+    let (terminal, terminal_args) = detect_terminal(config)?;
+
+    let mut wrapped_args = terminal_args;
+    wrapped_args.push(exe_path.to_string_lossy().into_owned());
+    wrapped_args.extend(args.iter().cloned());
+
+    Some((terminal, wrapped_args))
+}
+
+/// Pick the terminal emulator: config override first, then `$TERMINAL`,
+/// then the first known terminal installed on `PATH`
+fn detect_terminal(config: &TerminalConfig) -> Option<(PathBuf, Vec<String>)> {
+    if let Some(command) = config.command.as_deref().filter(|c| !c.is_empty()) {
+        let args = config
+            .args
+            .clone()
+            .unwrap_or_else(|| terminal_convention(command));
+        return Some((find_executable(command)?, args));
+    }
+
+    if let Ok(command) = env::var("TERMINAL")
+        && let Some(path) = find_executable(&command)
+    {
+        return Some((path, terminal_convention(&command)));
+    }
+
+    KNOWN_TERMINALS.iter().find_map(|(name, args)| {
+        find_executable(name).map(|path| (path, args.iter().map(|a| a.to_string()).collect()))
+    })
+}
+
+/// Arguments a terminal expects before the command, defaulting to the common `-e`
+fn terminal_convention(command: &str) -> Vec<String> {
+    let name = Path::new(command)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or(command);
+
+    KNOWN_TERMINALS
+        .iter()
+        .find(|(known, _)| *known == name)
+        .map_or_else(
+            || vec!["-e".to_string()],
+            |(_, args)| args.iter().map(|a| a.to_string()).collect(),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_terminal_convention() {
+        let cases: &[(&str, &[&str])] = &[
+            ("foot", &[]),
+            ("kitty", &[]),
+            ("alacritty", &["-e"]),
+            ("wezterm", &["start", "--"]),
+            ("gnome-terminal", &["--"]),
+            ("kgx", &["--"]),
+            ("konsole", &["-e"]),
+            ("xfce4-terminal", &["-x"]),
+            ("/usr/bin/gnome-terminal", &["--"]),
+            ("some-new-terminal", &["-e"]),
+        ];
+
+        for (command, args) in cases {
+            assert_eq!(terminal_convention(command), *args, "{}", command);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_wrap_command() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = env::temp_dir().join(format!("launchdock-terminal-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let wrap = |name: &str, args: Option<&[&str]>| {
+            let terminal = dir.join(name);
+            std::fs::write(&terminal, "").unwrap();
+            std::fs::set_permissions(&terminal, std::fs::Permissions::from_mode(0o755)).unwrap();
+            let config = TerminalConfig {
+                command: Some(terminal.to_string_lossy().into_owned()),
+                args: args.map(|args| args.iter().map(|a| a.to_string()).collect()),
+            };
+            let (path, wrapped) =
+                wrap_command(&config, Path::new("/usr/bin/htop"), &["-d".to_string()]).unwrap();
+            assert_eq!(path, terminal);
+            wrapped
+        };
+
+        assert_eq!(wrap("foot", None), ["/usr/bin/htop", "-d"]);
+        assert_eq!(wrap("kitty", None), ["/usr/bin/htop", "-d"]);
+        assert_eq!(wrap("gnome-terminal", None), ["--", "/usr/bin/htop", "-d"]);
+        assert_eq!(wrap("konsole", None), ["-e", "/usr/bin/htop", "-d"]);
+        assert_eq!(
+            wrap("wezterm", None),
+            ["start", "--", "/usr/bin/htop", "-d"]
+        );
+        // Configured arguments replace the convention
+        assert_eq!(
+            wrap("konsole", Some(&["--hold", "-e"])),
+            ["--hold", "-e", "/usr/bin/htop", "-d"]
+        );
+
+        // A configured terminal that isn't installed can't wrap anything
+        let missing = TerminalConfig {
+            command: Some(dir.join("missing").to_string_lossy().into_owned()),
+            args: None,
+        };
+        assert!(wrap_command(&missing, Path::new("/usr/bin/htop"), &[]).is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,20 +1,46 @@
-use ::image::{ImageBuffer, Rgb, codecs::png::PngEncoder};
+use iced::futures::channel::oneshot;
 use iced::{
     Alignment, Background, Color, Element, Length, Padding, Size,
     daemon::Appearance,
     keyboard::{self},
-    widget::{column, container, image, row, scrollable, text},
+    widget::{column, container, image, rich_text, row, scrollable, span, text},
     window,
 };
-use rand::{Rng, SeedableRng};
-use rand_chacha::ChaCha8Rng;
-use std::process::Command;
-
-use crate::apps::{self, AppInfo};
+use std::path::Path;
+use std::process::{Child, Command};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use crate::apps::{self, AppAction, AppInfo};
+use crate::config::Config;
+use crate::favorites::Favorites;
+use crate::history::{self, History};
+use crate::icons::{self, IconCache, IconKey};
+use crate::providers::{
+    self, Action, AppProvider, CalculatorProvider, FileProvider, Provider, ResultItem, Script,
+    SystemProvider, TimeZoneProvider, UnitProvider,
+};
+use crate::terminal;
 
 use crate::logs;
 
 pub fn run_ui(all_apps: Vec<AppInfo>) -> Result<(), Box<dyn std::error::Error>> {
+    let config = Config::load();
+    let apps = AppProvider::new(all_apps, &config, History::load(), Favorites::load());
+    let providers: Vec<Box<dyn Provider>> = vec![
+        Box::new(SystemProvider::new(
+            apps::system_commands(&config.icons),
+            config.search.strip_diacritics,
+        )),
+        Box::new(CalculatorProvider),
+        Box::new(UnitProvider),
+        Box::new(TimeZoneProvider),
+    ];
+    let scripts = Script::discover(&config);
+    let files = FileProvider::new(&config.files);
+
     iced::application("launchdock", update, view)
         .subscription(subscription)
         .style(|_, _| Appearance {
@@ -43,7 +69,12 @@ pub fn run_ui(all_apps: Vec<AppInfo>) -> Result<(), Box<dyn std::error::Error>>
             },
             ..Default::default()
         })
-        .run_with(move || (AppState::new(all_apps), iced::Task::none()))?;
+        .run_with(move || {
+            let mut state = AppState::new(apps, providers, scripts, files, config);
+            state.refresh_results();
+            let task = state.request_icons();
+            (state, task)
+        })?;
 
     Ok(())
 }
@@ -52,135 +83,426 @@ pub fn run_ui(all_apps: Vec<AppInfo>) -> Result<(), Box<dyn std::error::Error>>
 pub enum Message {
     InputChanged(String),
     KeyPressed(keyboard::Key, keyboard::Modifiers),
+    IconLoaded(IconKey, Option<image::Handle>),
+    /// Items a background provider returned for the query it was run with
+    BackgroundResults(String, Vec<ResultItem>),
     IgnoreEvent,
 }
 
 struct AppState {
-    all_apps: Vec<AppInfo>,
+    /// Installed applications; kept apart from `providers` as app results
+    /// also have desktop actions and can be pinned
+    apps: AppProvider,
+    /// Every other source of results answering right away
+    providers: Vec<Box<dyn Provider>>,
+    /// Providers run in the background, whose items are merged as they arrive
+    scripts: Vec<Script>,
+    /// Searches files in the background instead of the other providers when
+    /// the query starts with its prefix
+    files: FileProvider,
+    config: Config,
     search_query: String,
     selected_index: usize,
-    current_filtered_apps: Vec<AppInfo>,
+    /// Items of `apps` and `providers` for the current query
+    provider_items: Vec<ResultItem>,
+    /// Items of the background providers that have answered the current
+    /// query so far
+    background_items: Vec<ResultItem>,
+    /// Number of queries typed so far; background work for an earlier one
+    /// sees it change and stops
+    generation: Arc<AtomicUsize>,
+    /// Merged items of all providers, in display order
+    results: Vec<ResultItem>,
+    /// Whether the desktop actions of the selected app are listed instead of results
+    action_list_open: bool,
+    selected_action: usize,
+    icons: IconCache,
 }
 
 impl AppState {
-    fn new(all_apps: Vec<AppInfo>) -> Self {
+    fn new(
+        apps: AppProvider,
+        providers: Vec<Box<dyn Provider>>,
+        scripts: Vec<Script>,
+        files: FileProvider,
+        config: Config,
+    ) -> Self {
         Self {
-            all_apps,
+            apps,
+            providers,
+            scripts,
+            files,
+            config,
             selected_index: 0,
             search_query: String::new(),
-            current_filtered_apps: Vec::new(),
+            provider_items: Vec::new(),
+            background_items: Vec::new(),
+            generation: Arc::new(AtomicUsize::new(0)),
+            results: Vec::new(),
+            action_list_open: false,
+            selected_action: 0,
+            icons: IconCache::default(),
         }
     }
 
-    pub fn filtered_apps(&self) -> Vec<&AppInfo> {
-        if self.search_query.is_empty() {
-            return Vec::new();
+    /// Icons of the rows currently on screen, in display order
+    fn visible_icon_keys(&self) -> Vec<IconKey> {
+        match self.selected_app() {
+            Some(app) if self.action_list_open => std::iter::once(self.icons.key(
+                app.icon_path.as_deref(),
+                &app.name,
+                HEADER_ICON_SIZE,
+            ))
+            .chain(
+                app.actions
+                    .iter()
+                    .take(DISPLAY_COUNT)
+                    .map(|action| self.action_icon_key(app, action)),
+            )
+            .collect(),
+            _ => self
+                .results
+                .iter()
+                .map(|item| {
+                    self.icons
+                        .key(item.icon_path.as_deref(), &item.title, apps::ICON_SIZE)
+                })
+                .collect(),
         }
+    }
 
-        let query_lower = self.search_query.to_ascii_lowercase();
-        let query_chars: Vec<char> = query_lower.chars().collect();
+    /// An action's own icon, falling back to the icon of its application
+    fn action_icon_key(&self, app: &AppInfo, action: &AppAction) -> IconKey {
+        match &action.icon_path {
+            Some(icon_path) => self
+                .icons
+                .key(Some(icon_path), &action.name, apps::ICON_SIZE),
+            None => self
+                .icons
+                .key(app.icon_path.as_deref(), &app.name, apps::ICON_SIZE),
+        }
+    }
+
+    /// Start decoding every visible icon that isn't loaded or loading yet
+    fn request_icons(&mut self) -> iced::Task<Message> {
+        let persist = self.config.icons.disk_cache;
+        let tasks: Vec<_> = self
+            .visible_icon_keys()
+            .into_iter()
+            .filter(|key| self.icons.begin_load(key))
+            .map(|key| {
+                iced::Task::perform(
+                    async move {
+                        let handle = icons::load(&key, persist);
+                        (key, handle)
+                    },
+                    |(key, handle)| Message::IconLoaded(key, handle),
+                )
+            })
+            .collect();
+
+        iced::Task::batch(tasks)
+    }
 
-        let mut matches: Vec<(&AppInfo, f32)> = Vec::new();
+    /// Ask every provider about the current query and merge their items,
+    /// selecting the first one. Background providers are asked separately by
+    /// `run_background`.
+    fn refresh_results(&mut self) {
+        self.selected_index = 0;
+        self.close_action_list();
+
+        self.provider_items.clear();
+        // A file search lists nothing but files
+        if self.files.file_query(&self.search_query).is_none() {
+            self.provider_items = self.apps.query(&self.search_query, DISPLAY_COUNT);
+            for provider in &mut self.providers {
+                self.provider_items
+                    .extend(provider.query(&self.search_query, DISPLAY_COUNT));
+            }
+        }
+        self.background_items.clear();
+        self.merge_results();
+    }
 
-        for app in &self.all_apps {
-            let app_name_lower = app.name.to_ascii_lowercase();
-            let app_chars: Vec<char> = app_name_lower.chars().collect();
+    fn merge_results(&mut self) {
+        let items = self
+            .provider_items
+            .iter()
+            .chain(&self.background_items)
+            .cloned()
+            .collect();
+        self.results = providers::merge(items, DISPLAY_COUNT);
+    }
 
-            // Find subsequence positions
-            let mut positions = Vec::new();
-            let mut app_idx = 0;
-            let mut found_all = true;
+    /// Run the file search, or else every provider script, for the current
+    /// query in the background
+    fn run_background(&self) -> iced::Task<Message> {
+        if self.search_query.is_empty() {
+            return iced::Task::none();
+        }
 
-            for &query_char in &query_chars {
-                // Find next occurrence of query_char in app_chars starting from app_idx
-                while app_idx < app_chars.len() && app_chars[app_idx] != query_char {
-                    app_idx += 1;
-                }
+        let tasks: Vec<_> = if self.files.file_query(&self.search_query).is_some() {
+            let mut files = self.files.clone();
+            vec![self.run_in_background(move |query, _| files.query(query, DISPLAY_COUNT))]
+        } else {
+            self.scripts
+                .iter()
+                .map(|script| {
+                    let script = script.clone();
+                    self.run_in_background(move |query, cancelled| {
+                        script.query_unless(query, DISPLAY_COUNT, cancelled)
+                    })
+                })
+                .collect()
+        };
 
-                if app_idx >= app_chars.len() {
-                    found_all = false;
-                    break;
-                }
+        iced::Task::batch(tasks)
+    }
 
-                positions.push(app_idx);
-                app_idx += 1; // Move past this match for next search
+    /// Run `search` for the current query on a thread of its own, as it may
+    /// block for as long as a script takes, once typing has paused. `search`
+    /// is passed a check of whether a newer query has replaced this one.
+    fn run_in_background(
+        &self,
+        search: impl FnOnce(&str, &dyn Fn() -> bool) -> Vec<ResultItem> + Send + 'static,
+    ) -> iced::Task<Message> {
+        let query = self.search_query.clone();
+        let generation = Arc::clone(&self.generation);
+        let current = generation.load(Ordering::SeqCst);
+        let (sender, receiver) = oneshot::channel();
+
+        thread::spawn(move || {
+            let cancelled = || generation.load(Ordering::SeqCst) != current;
+            thread::sleep(BACKGROUND_DELAY);
+            if cancelled() {
+                return;
             }
+            let items = search(&query, &cancelled);
+            let _ = sender.send((query, items));
+        });
+
+        iced::Task::perform(receiver, |answer| match answer {
+            Ok((query, items)) => Message::BackgroundResults(query, items),
+            // The query was replaced before its search started
+            Err(_) => Message::IgnoreEvent,
+        })
+    }
 
-            if !found_all {
-                continue; // Skip if not all query characters found in order
+    /// Merge a background provider's items into the results, keeping the
+    /// selected item selected wherever it moves
+    fn add_background_items(&mut self, items: Vec<ResultItem>) {
+        let selected = self.selected_item().cloned();
+        self.background_items.extend(items);
+        self.merge_results();
+
+        match selected.and_then(|item| self.results.iter().position(|other| *other == item)) {
+            Some(index) => self.selected_index = index,
+            None => {
+                self.selected_index = 0;
+                self.close_action_list();
             }
+        }
+    }
 
-            // Calculate score
-            let mut score = 0.0f32;
+    /// Show the results for a new query
+    fn set_query(&mut self, query: String) -> iced::Task<Message> {
+        self.search_query = query;
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        self.refresh_results();
+        iced::Task::batch([self.run_background(), self.request_icons()])
+    }
 
-            // Base score: prefer shorter names but give substantial base points
-            score += 1000.0 / (app.name.len() as f32).max(1.0);
+    /// Pin the selected app to the empty-query list, or unpin it
+    fn toggle_favorite(&mut self) {
+        let Some(&Action::App(index)) = self.selected_item().map(|item| &item.action) else {
+            return;
+        };
+        self.apps.toggle_favorite(index);
 
-            // Character proximity bonus - closer characters get higher score (most important)
-            if positions.len() > 1 {
-                let total_span = positions.last().unwrap() - positions.first().unwrap() + 1;
-                score += 1000.0 / (total_span as f32).max(1.0);
-            }
+        // The empty-query list shows favorites, so it changes with them
+        if self.search_query.is_empty() {
+            let selected_index = self.selected_index;
+            self.refresh_results();
+            self.selected_index = selected_index.min(self.results.len().saturating_sub(1));
+        }
+    }
 
-            // Consecutive character bonus
-            let mut consecutive_count = 0;
-            for window in positions.windows(2) {
-                if window[1] - window[0] == 1 {
-                    consecutive_count += 1;
+    fn selected_item(&self) -> Option<&ResultItem> {
+        self.results.get(self.selected_index)
+    }
+
+    /// The app of the selected item, unless another provider's item is selected
+    fn selected_app(&self) -> Option<&AppInfo> {
+        match self.selected_item()?.action {
+            Action::App(index) => Some(self.apps.app(index)),
+            _ => None,
+        }
+    }
+
+    /// Carry out the selected item's action, or launch the selected desktop
+    /// action while the action list is open, then close the launcher
+    fn activate_selected(&self) -> iced::Task<Message> {
+        let Some(item) = self.selected_item() else {
+            return iced::exit();
+        };
+
+        match &item.action {
+            Action::App(index) => {
+                let app = self.apps.app(*index);
+                match app.actions.get(self.selected_action) {
+                    Some(action) if self.action_list_open => {
+                        launch_action(app, action, &self.search_query, &self.config)
+                    }
+                    _ => launch_app(app, &self.search_query, &self.config),
                 }
             }
-            score += consecutive_count as f32 * 200.0;
-
-            // Early match bonus - matches earlier in the string get small bonus
-            let first_match_pos = positions[0];
-            score += 50.0 / (first_match_pos as f32 + 1.0);
+            Action::Run { program, args } => run_command(&item.title, program, args),
+            Action::Open(uri) => open_uri(&item.title, uri),
+            Action::File(path) => open_uri(&item.title, &path.to_string_lossy()),
+            Action::Copy(text) => {
+                logs::log_info(&format!("Copied: {}", item.title));
+                return iced::clipboard::write(text.clone()).chain(iced::exit());
+            }
+        }
+        iced::exit()
+    }
 
-            matches.push((app, score));
+    /// Show the selected file in the file manager and close the launcher;
+    /// any other item is activated as usual
+    fn reveal_selected(&self) -> iced::Task<Message> {
+        match self.selected_item().map(|item| &item.action) {
+            Some(Action::File(path)) => {
+                reveal_file(path);
+                iced::exit()
+            }
+            _ => self.activate_selected(),
         }
+    }
+
+    fn action_display_count(&self) -> usize {
+        self.selected_app()
+            .map_or(0, |app| app.actions.len().min(DISPLAY_COUNT))
+    }
 
-        // Sort by score (highest first)
-        matches.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+    fn open_action_list(&mut self) {
+        if self
+            .selected_app()
+            .is_some_and(|app| !app.actions.is_empty())
+        {
+            self.action_list_open = true;
+            self.selected_action = 0;
+        }
+    }
 
-        matches.into_iter().map(|(app, _)| app).collect()
+    fn close_action_list(&mut self) {
+        self.action_list_open = false;
+        self.selected_action = 0;
     }
 }
 
 const DISPLAY_COUNT: usize = 7;
 
+/// Pause in typing before the background providers are asked, so that a
+/// script isn't started for every keystroke
+const BACKGROUND_DELAY: Duration = Duration::from_millis(120);
+
+/// Icon size of the app shown above its action list
+const HEADER_ICON_SIZE: u32 = 32;
+
 fn update(state: &mut AppState, message: Message) -> iced::Task<Message> {
     match message {
         Message::IgnoreEvent => iced::Task::none(),
 
-        Message::InputChanged(value) => {
-            state.search_query = value;
-            state.selected_index = 0;
-            state.current_filtered_apps.clear();
-            state.current_filtered_apps = state.filtered_apps().into_iter().cloned().collect();
-            iced::Task::none()
+        Message::IconLoaded(key, handle) => {
+            state.icons.insert(key, handle);
+            // A failed icon is replaced by its fallback, which needs loading too
+            state.request_icons()
+        }
+
+        Message::InputChanged(value) => state.set_query(value),
+
+        // Answers to an earlier query arrive late and are dropped
+        Message::BackgroundResults(query, items) => {
+            if query != state.search_query || items.is_empty() {
+                return iced::Task::none();
+            }
+            state.add_background_items(items);
+            state.request_icons()
         }
 
         Message::KeyPressed(key, modifiers) => {
             match (key, modifiers) {
+                (keyboard::Key::Named(keyboard::key::Named::Escape), _)
+                    if state.action_list_open =>
+                {
+                    state.close_action_list();
+                    iced::Task::none()
+                }
+
                 (keyboard::Key::Named(keyboard::key::Named::Escape), _) => iced::exit(),
 
-                (keyboard::Key::Named(keyboard::key::Named::Enter), _) => {
-                    if let Some(app) = state.current_filtered_apps.get(state.selected_index) {
-                        launch_app(app);
+                (keyboard::Key::Named(keyboard::key::Named::Enter), modifiers)
+                    if modifiers.control() =>
+                {
+                    state.reveal_selected()
+                }
+
+                (keyboard::Key::Named(keyboard::key::Named::Enter), _) => state.activate_selected(),
+
+                (keyboard::Key::Named(keyboard::key::Named::Tab), _) => {
+                    if state.action_list_open {
+                        state.close_action_list();
+                    } else {
+                        state.open_action_list();
+                    }
+                    state.request_icons()
+                }
+
+                (keyboard::Key::Named(keyboard::key::Named::ArrowRight), _) => {
+                    state.open_action_list();
+                    state.request_icons()
+                }
+
+                (keyboard::Key::Named(keyboard::key::Named::ArrowLeft), _) => {
+                    state.close_action_list();
+                    iced::Task::none()
+                }
+
+                (keyboard::Key::Named(keyboard::key::Named::ArrowDown), _)
+                    if state.action_list_open =>
+                {
+                    let display_count = state.action_display_count();
+                    if display_count > 0 {
+                        state.selected_action = (state.selected_action + 1) % display_count;
+                    }
+                    iced::Task::none()
+                }
+
+                (keyboard::Key::Named(keyboard::key::Named::ArrowUp), _)
+                    if state.action_list_open =>
+                {
+                    let display_count = state.action_display_count();
+                    if display_count > 0 {
+                        state.selected_action = if state.selected_action == 0 {
+                            display_count - 1
+                        } else {
+                            state.selected_action - 1
+                        };
                     }
-                    iced::exit()
+                    iced::Task::none()
                 }
 
                 (keyboard::Key::Named(keyboard::key::Named::ArrowDown), _) => {
-                    if !state.current_filtered_apps.is_empty() {
-                        let display_count = state.current_filtered_apps.len().min(DISPLAY_COUNT);
+                    if !state.results.is_empty() {
+                        let display_count = state.results.len().min(DISPLAY_COUNT);
                         state.selected_index = (state.selected_index + 1) % display_count;
                     }
                     iced::Task::none()
                 }
 
                 (keyboard::Key::Named(keyboard::key::Named::ArrowUp), _) => {
-                    if !state.current_filtered_apps.is_empty() {
-                        let display_count = state.current_filtered_apps.len().min(DISPLAY_COUNT);
+                    if !state.results.is_empty() {
+                        let display_count = state.results.len().min(DISPLAY_COUNT);
                         state.selected_index = if state.selected_index == 0 {
                             display_count - 1
                         } else {
@@ -191,24 +513,40 @@ fn update(state: &mut AppState, message: Message) -> iced::Task<Message> {
                 }
 
                 (keyboard::Key::Named(keyboard::key::Named::Backspace), _) => {
-                    state.search_query.pop();
-                    state.selected_index = 0;
-                    state.current_filtered_apps.clear();
-                    state.current_filtered_apps =
-                        state.filtered_apps().into_iter().cloned().collect();
+                    let mut new_search = state.search_query.clone();
+                    new_search.pop();
+                    state.set_query(new_search)
+                }
+
+                (keyboard::Key::Character(ref c), modifiers)
+                    if modifiers.control() && c.as_str() == "p" && !state.action_list_open =>
+                {
+                    state.toggle_favorite();
+                    state.request_icons()
+                }
+
+                (keyboard::Key::Character(ref c), modifiers)
+                    if modifiers.logo() && state.action_list_open =>
+                {
+                    if let Ok(num) = c.parse::<usize>()
+                        && num >= 1
+                        && let Some(app) = state.selected_app()
+                        && let Some(action) =
+                            app.actions.get(num - 1).filter(|_| num <= DISPLAY_COUNT)
+                    {
+                        launch_action(app, action, &state.search_query, &state.config);
+                        return iced::exit();
+                    }
                     iced::Task::none()
                 }
 
                 (keyboard::Key::Character(ref c), modifiers) if modifiers.logo() => {
                     if let Ok(num) = c.parse::<usize>()
                         && num >= 1
-                        && num <= state.current_filtered_apps.len().min(DISPLAY_COUNT)
+                        && num <= state.results.len().min(DISPLAY_COUNT)
                     {
-                        let index = num - 1;
-                        if let Some(app) = state.current_filtered_apps.get(index) {
-                            launch_app(app);
-                            return iced::exit();
-                        }
+                        state.selected_index = num - 1;
+                        return state.activate_selected();
                     }
                     iced::Task::none()
                 }
@@ -261,78 +599,57 @@ fn view(state: &AppState) -> Element<'_, Message> {
         text_color: None,
     });
 
-    let max_results = state.current_filtered_apps.len().min(DISPLAY_COUNT);
-
-    let app_items: Vec<Element<Message>> = state
-        .current_filtered_apps
-        .iter()
-        .take(max_results)
-        .enumerate()
-        .map(|(index, app)| {
-            let is_selected = index == state.selected_index;
-
-            let icon = extract_app_icon(app);
-            let icon_widget = image(icon).width(48).height(48);
-
-            let app_name = text(&app.name)
-                .size(24)
-                .color(Color::from_rgb(0.96, 0.96, 0.96));
-
-            let shortcut_symbol = {
-                #[cfg(target_os = "macos")]
-                let symbol = "⌘ ";
-
-                #[cfg(target_os = "windows")]
-                let symbol = "⊞ ";
-
-                #[cfg(target_os = "linux")]
-                let symbol = "◆ ";
-
-                #[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux",)))]
-                let symbol = "Logo+";
-
-                symbol
-            };
-
-            let shortcut = text(format!("{}{}", shortcut_symbol, index + 1))
-                .size(18)
-                .color(Color::from_rgb(0.8, 0.8, 0.8));
-
-            let content = row![
-                icon_widget,
-                app_name,
-                iced::widget::horizontal_space(),
-                shortcut
-            ]
-            .padding(Padding {
-                top: 0.0,
-                right: 12.0,
-                bottom: 0.0,
-                left: 0.0,
-            })
-            .spacing(12)
-            .align_y(Alignment::Center);
-
-            container(content)
-                .padding(Padding::from(8))
-                .width(Length::Fill)
-                .style(move |_| container::Style {
-                    background: Some(Background::Color(Color::from_rgba(0.0, 0.0, 0.0, 0.84))),
-                    border: iced::Border {
-                        color: if is_selected {
-                            Color::from_rgb(0.149, 0.498, 0.749)
-                        } else {
-                            Color::from_rgba(1.0, 1.0, 1.0, 0.8)
+    let app_items: Vec<Element<Message>> =
+        match state.selected_app() {
+            // Secondary list: the selected app followed by its desktop actions
+            Some(app) if state.action_list_open => {
+                let header_icon = state.icons.handle(&state.icons.key(
+                    app.icon_path.as_deref(),
+                    &app.name,
+                    HEADER_ICON_SIZE,
+                ));
+
+                std::iter::once(action_list_header(app, header_icon))
+                    .chain(app.actions.iter().take(DISPLAY_COUNT).enumerate().map(
+                        |(index, action)| {
+                            result_row(
+                                state.icons.handle(&state.action_icon_key(app, action)),
+                                &action.name,
+                                &[],
+                                &[],
+                                index,
+                                index == state.selected_action,
+                                false,
+                                false,
+                            )
                         },
-                        width: 2.0,
-                        radius: 6.0.into(),
-                    },
-                    shadow: iced::Shadow::default(),
-                    text_color: None,
+                    ))
+                    .collect()
+            }
+            _ => state
+                .results
+                .iter()
+                .enumerate()
+                .map(|(index, item)| {
+                    let icon =
+                        state
+                            .icons
+                            .key(item.icon_path.as_deref(), &item.title, apps::ICON_SIZE);
+                    let is_pinned =
+                        matches!(item.action, Action::App(app) if state.apps.is_favorite(app));
+                    result_row(
+                        state.icons.handle(&icon),
+                        &item.title,
+                        &item.title_matches,
+                        &item.subtitle,
+                        index,
+                        index == state.selected_index,
+                        is_pinned,
+                        item.approximate,
+                    )
                 })
-                .into()
-        })
-        .collect();
+                .collect(),
+        };
 
     let app_list = column(app_items).spacing(2);
 
@@ -380,179 +697,419 @@ fn view(state: &AppState) -> Element<'_, Message> {
         .into()
 }
 
-/// Launch an application using platform-specific methods
-pub fn launch_app(app: &AppInfo) {
-    logs::log_info(&format!("Launching: {}", app.name));
+/// Selection border, pinned star and matched characters
+const ACCENT_COLOR: Color = Color::from_rgb(0.149, 0.498, 0.749);
+
+/// Render one numbered row of the results list, with the matched characters of
+/// its title and subtitle in the accent color, starred if it is a pinned
+/// favorite and dimmed with a "≈" if it only matched after correcting typos
+#[allow(clippy::too_many_arguments)]
+fn result_row<'a>(
+    icon: image::Handle,
+    title: &'a str,
+    title_matches: &[usize],
+    subtitle: &[(String, Vec<usize>)],
+    index: usize,
+    is_selected: bool,
+    is_pinned: bool,
+    is_approximate: bool,
+) -> Element<'a, Message> {
+    let icon_widget = image(icon)
+        .width(apps::ICON_SIZE as f32)
+        .height(apps::ICON_SIZE as f32);
+
+    let name_color = if is_approximate {
+        Color::from_rgb(0.7, 0.7, 0.7)
+    } else {
+        Color::from_rgb(0.96, 0.96, 0.96)
+    };
+    let app_name = rich_text(highlighted_spans(title, title_matches, name_color)).size(24);
 
-    let result = {
-        #[cfg(windows)]
-        {
-            Command::new("cmd")
-                .args(["/c", "start", ""])
-                .arg(&app.exe_path)
-                .spawn()
-        }
+    // Secondary fields that matched, in a smaller line below the name
+    let subtitle_spans: Vec<_> = subtitle
+        .iter()
+        .enumerate()
+        .flat_map(|(field_index, (field, positions))| {
+            let (excerpt, positions) = subtitle_excerpt(field, positions);
+            let separator = (field_index > 0).then(|| span(" · "));
+            separator.into_iter().chain(highlighted_spans(
+                &excerpt,
+                &positions,
+                Color::from_rgb(0.7, 0.7, 0.7),
+            ))
+        })
+        .collect();
+    let title_column = if subtitle_spans.is_empty() {
+        column![app_name]
+    } else {
+        column![
+            app_name,
+            rich_text(subtitle_spans)
+                .size(14)
+                .color(Color::from_rgb(0.7, 0.7, 0.7))
+        ]
+    };
 
-        #[cfg(target_os = "macos")]
-        {
-            let exe_path_str = app.exe_path.to_string_lossy();
+    let approximate = text(if is_approximate { "≈" } else { "" })
+        .size(18)
+        .color(Color::from_rgb(0.6, 0.6, 0.6));
 
-            match exe_path_str.as_ref() {
-                path if path.starts_with("osascript")
-                    || path.starts_with("pmset")
-                    || path.starts_with("/System/Library/CoreServices") =>
-                {
-                    Command::new("sh").arg("-c").arg(path).spawn()
-                }
+    let shortcut_symbol = {
+        #[cfg(target_os = "macos")]
+        let symbol = "⌘ ";
 
-                _ => Command::new("open").arg(&app.exe_path).spawn(),
-            }
-        }
+        #[cfg(target_os = "windows")]
+        let symbol = "⊞ ";
 
         #[cfg(target_os = "linux")]
-        {
-            let exe_string = app.exe_path.to_string_lossy();
+        let symbol = "◆ ";
 
-            match exe_string.as_ref() {
-                s if s.starts_with("systemctl") || s.starts_with("loginctl") => {
-                    Command::new("sh").arg("-c").arg(s).spawn()
-                }
-                _ => Command::new(&app.exe_path).spawn(),
-            }
-        }
+        #[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux",)))]
+        let symbol = "Logo+";
+
+        symbol
     };
 
-    if let Err(e) = result {
-        logs::log_error(&format!("Failed to launch {}: {}", app.name, e));
-    }
+    let shortcut = text(format!("{}{}", shortcut_symbol, index + 1))
+        .size(18)
+        .color(Color::from_rgb(0.8, 0.8, 0.8));
+
+    let pin = text(if is_pinned { "★" } else { "" })
+        .size(18)
+        .color(ACCENT_COLOR);
+
+    let content = row![
+        icon_widget,
+        title_column,
+        approximate,
+        iced::widget::horizontal_space(),
+        pin,
+        shortcut
+    ]
+    .padding(Padding {
+        top: 0.0,
+        right: 12.0,
+        bottom: 0.0,
+        left: 0.0,
+    })
+    .spacing(12)
+    .align_y(Alignment::Center);
+
+    container(content)
+        .padding(Padding::from(8))
+        .width(Length::Fill)
+        .style(move |_| container::Style {
+            background: Some(Background::Color(Color::from_rgba(0.0, 0.0, 0.0, 0.84))),
+            border: iced::Border {
+                color: if is_selected {
+                    ACCENT_COLOR
+                } else {
+                    Color::from_rgba(1.0, 1.0, 1.0, 0.8)
+                },
+                width: 2.0,
+                radius: 6.0.into(),
+            },
+            shadow: iced::Shadow::default(),
+            text_color: None,
+        })
+        .into()
 }
 
-/// Extract icon and return iced handle
-fn extract_app_icon(app: &AppInfo) -> iced::widget::image::Handle {
-    if let Ok(Some(icon_data)) = apps::extract_icon(app) {
-        return iced::widget::image::Handle::from_bytes(icon_data);
+/// Split `text` into spans, with the characters at `positions` in the accent color
+fn highlighted_spans<'a>(
+    text: &str,
+    positions: &[usize],
+    color: Color,
+) -> Vec<text::Span<'a, Message>> {
+    let mut spans = Vec::new();
+    let mut run = String::new();
+    let mut run_matched = false;
+
+    for (index, c) in text.chars().enumerate() {
+        let matched = positions.binary_search(&index).is_ok();
+        if matched != run_matched && !run.is_empty() {
+            spans.push(span(std::mem::take(&mut run)).color(if run_matched {
+                ACCENT_COLOR
+            } else {
+                color
+            }));
+        }
+        run_matched = matched;
+        run.push(c);
+    }
+    if !run.is_empty() {
+        spans.push(span(run).color(if run_matched { ACCENT_COLOR } else { color }));
     }
 
-    generate_fallback_icon(&app.name)
+    spans
 }
 
-/// Generate a deterministic fallback icon for apps without icons
-fn generate_fallback_icon(app_name: &str) -> iced::widget::image::Handle {
-    // Create a deterministic but random-looking 48x48 icon based on app name
-    let base_seed = app_name.chars().map(|c| c as u64).sum::<u64>();
-    let mut img = ImageBuffer::new(64, 64);
+/// Longest subtitle shown for one field, in characters
+const SUBTITLE_MAX_CHARS: usize = 48;
 
-    // Generate a simple pixelated pattern
-    for y in 0..64 {
-        for x in 0..64 {
-            // Create blocks of 8x8 pixels for pixelated effect
-            let block_x = x / 8;
-            let block_y = y / 8;
-            let block_seed = block_x * 8 + block_y;
+/// Shorten a long field (usually a description) to the part around its first
+/// match, shifting the matched positions to the shortened text
+fn subtitle_excerpt(field: &str, positions: &[usize]) -> (String, Vec<usize>) {
+    let char_count = field.chars().count();
+    if char_count <= SUBTITLE_MAX_CHARS {
+        return (field.to_string(), positions.to_vec());
+    }
 
-            let mut block_rng = ChaCha8Rng::seed_from_u64(base_seed + block_seed as u64);
+    // Start a few characters before the first match, without running past the end
+    let first = positions.first().copied().unwrap_or(0);
+    let start = first.saturating_sub(8).min(char_count - SUBTITLE_MAX_CHARS);
+    let end = start + SUBTITLE_MAX_CHARS;
+
+    let mut excerpt = String::new();
+    let offset = if start > 0 {
+        excerpt.push('…');
+        start - 1
+    } else {
+        0
+    };
+    excerpt.extend(field.chars().skip(start).take(SUBTITLE_MAX_CHARS));
+    if end < char_count {
+        excerpt.push('…');
+    }
 
-            let intensity = if block_rng.r#gen::<f32>() > 0.5 {
-                200u8
-            } else {
-                50u8
-            };
-            let color = [intensity, intensity, intensity];
+    let positions = positions
+        .iter()
+        .filter(|&&position| position >= start && position < end)
+        .map(|&position| position - offset)
+        .collect();
 
-            img.put_pixel(x, y, Rgb(color));
-        }
+    (excerpt, positions)
+}
+
+/// Render the app whose actions are listed, above the action rows
+fn action_list_header(app: &AppInfo, icon: image::Handle) -> Element<'_, Message> {
+    let content = row![
+        image(icon)
+            .width(HEADER_ICON_SIZE as f32)
+            .height(HEADER_ICON_SIZE as f32),
+        text(&app.name)
+            .size(18)
+            .color(Color::from_rgb(0.8, 0.8, 0.8)),
+        iced::widget::horizontal_space(),
+        text("← Back")
+            .size(14)
+            .color(Color::from_rgb(0.6, 0.6, 0.6)),
+    ]
+    .padding(Padding {
+        top: 0.0,
+        right: 12.0,
+        bottom: 0.0,
+        left: 8.0,
+    })
+    .spacing(12)
+    .align_y(Alignment::Center);
+
+    container(content)
+        .padding(Padding::from(4))
+        .width(Length::Fill)
+        .style(|_| container::Style {
+            background: Some(Background::Color(Color::from_rgba(0.0, 0.0, 0.0, 0.84))),
+            border: iced::Border {
+                color: Color::from_rgba(1.0, 1.0, 1.0, 0.2),
+                width: 1.0,
+                radius: 6.0.into(),
+            },
+            shadow: iced::Shadow::default(),
+            text_color: None,
+        })
+        .into()
+}
+
+/// Launch an application using platform-specific methods, recording the
+/// launch and the query it was found with in the history
+pub fn launch_app(app: &AppInfo, query: &str, config: &Config) {
+    logs::log_info(&format!("Launching: {} [{}]", app.name, app.id));
+
+    match spawn_command(&app.exe_path, &app.args, app.terminal, config) {
+        Ok(_) => record_launch(app, query),
+        Err(e) => logs::log_error(&format!("Failed to launch {}: {}", app.name, e)),
     }
+}
 
-    // Convert to bytes
-    let mut bytes = Vec::new();
-    let encoder = PngEncoder::new(&mut bytes);
-    img.write_with_encoder(encoder).unwrap_or_else(|_| {
-        logs::log_error("Failed to encode generated icon");
-    });
+/// Launch one of an application's desktop actions; counts as a launch of the app
+pub fn launch_action(app: &AppInfo, action: &AppAction, query: &str, config: &Config) {
+    logs::log_info(&format!(
+        "Launching: {} [{}] ({})",
+        app.name, app.id, action.name
+    ));
+
+    match spawn_command(&action.exe_path, &action.args, app.terminal, config) {
+        Ok(_) => record_launch(app, query),
+        Err(e) => logs::log_error(&format!(
+            "Failed to launch {} ({}): {}",
+            app.name, action.name, e
+        )),
+    }
+}
 
-    iced::widget::image::Handle::from_bytes(bytes)
+fn record_launch(app: &AppInfo, query: &str) {
+    if let Err(e) = history::record(app, query) {
+        logs::log_error(&format!("Failed to record launch of {}: {}", app.name, e));
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::path::PathBuf;
+fn spawn_command(
+    exe_path: &Path,
+    args: &[String],
+    terminal: bool,
+    config: &Config,
+) -> std::io::Result<Child> {
+    if terminal {
+        let (terminal_path, terminal_args) =
+            terminal::wrap_command(&config.terminal, exe_path, args).ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    "No terminal emulator found; set [terminal] command in config.toml",
+                )
+            })?;
+        return spawn_command(&terminal_path, &terminal_args, false, config);
+    }
 
-    fn create_test_app(name: &str, path: &str) -> AppInfo {
-        AppInfo {
-            name: name.to_string(),
-            exe_path: PathBuf::from(path),
-            icon_path: None,
+    #[cfg(windows)]
+    {
+        Command::new("cmd")
+            .args(["/c", "start", ""])
+            .arg(exe_path)
+            .args(args)
+            .spawn()
+    }
+
+    // Bundles are opened by Launch Services; anything else is run directly
+    #[cfg(target_os = "macos")]
+    {
+        if exe_path.extension().is_some_and(|ext| ext == "app") {
+            Command::new("open").arg(exe_path).spawn()
+        } else {
+            Command::new(exe_path).args(args).spawn()
         }
     }
 
-    fn create_test_state(apps: Vec<AppInfo>, query: &str) -> AppState {
-        let mut state = AppState::new(apps);
-        state.search_query = query.to_string();
-        state
+    #[cfg(target_os = "linux")]
+    {
+        Command::new(exe_path).args(args).spawn()
+    }
+}
+
+/// Run the command of a provider's item directly; unlike apps, it isn't
+/// recorded in the history
+fn run_command(title: &str, program: &Path, args: &[String]) {
+    logs::log_info(&format!("Running: {}", title));
+
+    if let Err(e) = Command::new(program).args(args).spawn() {
+        logs::log_error(&format!("Failed to run {}: {}", title, e));
     }
+}
 
-    #[test]
-    fn test_basic_matching() {
-        let apps = vec![
-            create_test_app("firefox", "/usr/bin/firefox"),
-            create_test_app("photogravure", "/usr/bin/photogravure"),
-            create_test_app("gimp", "/usr/bin/gimp"),
-            create_test_app("gnome-video", "/usr/bin/gnome-video"),
-        ];
+/// Open a provider item's URI or file with the desktop's default handler
+fn open_uri(title: &str, uri: &str) {
+    logs::log_info(&format!("Opening: {} ({})", title, uri));
+
+    #[cfg(windows)]
+    let result = Command::new("cmd")
+        .args(["/c", "start", ""])
+        .arg(uri)
+        .spawn();
+
+    #[cfg(target_os = "macos")]
+    let result = Command::new("open").arg(uri).spawn();
 
-        let state = create_test_state(apps, "gv");
-        let results = state.filtered_apps();
+    #[cfg(target_os = "linux")]
+    let result = Command::new("xdg-open").arg(uri).spawn();
 
-        // Should have exactly 2 results: photogravure first, then gnome-video
-        assert_eq!(results.len(), 2);
-        assert_eq!(results[0].name, "photogravure");
-        assert_eq!(results[1].name, "gnome-video");
+    if let Err(e) = result {
+        logs::log_error(&format!("Failed to open {}: {}", uri, e));
     }
+}
 
-    #[test]
-    fn test_case_insensitive() {
-        let apps = vec![
-            create_test_app("Firefox", "/usr/bin/Firefox"),
-            create_test_app("GIMP", "/usr/bin/GIMP"),
-        ];
+/// Show a file selected in its folder in the file manager
+fn reveal_file(path: &Path) {
+    logs::log_info(&format!("Revealing: {}", path.display()));
+
+    #[cfg(windows)]
+    let result = {
+        use std::os::windows::process::CommandExt;
+        Command::new("explorer")
+            .raw_arg(format!("/select,\"{}\"", path.display()))
+            .spawn()
+    };
 
-        let state = create_test_state(apps.clone(), "fox");
-        let results = state.filtered_apps();
-        assert_eq!(results.len(), 1);
-        assert_eq!(results[0].name, "Firefox");
+    #[cfg(target_os = "macos")]
+    let result = Command::new("open").arg("-R").arg(path).spawn();
+
+    // File managers implementing the freedesktop FileManager1 interface select
+    // the file; without one, its folder is opened instead
+    #[cfg(target_os = "linux")]
+    let result = Command::new("sh")
+        .arg("-c")
+        .arg(
+            "dbus-send --session --print-reply --dest=org.freedesktop.FileManager1 \
+             /org/freedesktop/FileManager1 org.freedesktop.FileManager1.ShowItems \
+             \"array:string:$1\" string: >/dev/null 2>&1 || xdg-open \"$2\"",
+        )
+        .arg("sh")
+        .arg(file_uri(path))
+        .arg(path.parent().unwrap_or(path))
+        .spawn();
 
-        let state = create_test_state(apps, "GIM");
-        let results = state.filtered_apps();
-        assert_eq!(results.len(), 1);
-        assert_eq!(results[0].name, "GIMP");
+    if let Err(e) = result {
+        logs::log_error(&format!("Failed to reveal {}: {}", path.display(), e));
     }
+}
 
-    #[test]
-    fn test_empty_query() {
-        let apps = vec![
-            create_test_app("firefox", "/usr/bin/firefox"),
-            create_test_app("gimp", "/usr/bin/gimp"),
-        ];
+/// `file://` URI of an absolute path, with everything but unreserved
+/// characters percent-encoded (including commas, which D-Bus arrays split at)
+#[cfg(target_os = "linux")]
+fn file_uri(path: &Path) -> String {
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut uri = String::from("file://");
+    for &byte in path.as_os_str().as_bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        let state = create_test_state(apps, "");
-        let results = state.filtered_apps();
-        assert_eq!(results.len(), 0); // Empty query returns no results
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_file_uri() {
+        assert_eq!(
+            file_uri(Path::new("/home/user/Tax 2025, final.pdf")),
+            "file:///home/user/Tax%202025%2C%20final.pdf"
+        );
+        assert_eq!(
+            file_uri(Path::new("/tmp/Größe")),
+            "file:///tmp/Gr%C3%B6%C3%9Fe"
+        );
     }
 
     #[test]
-    fn test_no_matches() {
-        let apps = vec![
-            create_test_app("firefox", "/usr/bin/firefox"),
-            create_test_app("photogravure", "/usr/bin/photogravure"),
-            create_test_app("gimp", "/usr/bin/gimp"),
-            create_test_app("gnome-video", "/usr/bin/gnome-video"),
-        ];
-
-        let state = create_test_state(apps, "xyz");
-        let results = state.filtered_apps();
-
-        // Should have no results as no app contains x, y, z in sequence
-        assert_eq!(results.len(), 0);
+    fn test_subtitle_excerpt() {
+        let short = subtitle_excerpt("Web Browser", &[4, 5]);
+        assert_eq!(short, ("Web Browser".to_string(), vec![4, 5]));
+
+        let description = "A free and open-source raster graphics editor used for image \
+                           manipulation and retouching";
+        let (excerpt, positions) = subtitle_excerpt(description, &[61, 62, 63]);
+        assert!(excerpt.starts_with('…'));
+        assert_eq!(excerpt.chars().count(), SUBTITLE_MAX_CHARS + 1);
+        let matched: String = positions
+            .iter()
+            .map(|&position| excerpt.chars().nth(position).unwrap())
+            .collect();
+        assert_eq!(matched, "man");
     }
 }
@@ -0,0 +1,390 @@
+use crate::logs;
+use std::collections::{HashMap, HashSet};
+use std::ffi::{CString, OsStr};
+use std::fs;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use std::{mem, ptr, thread};
+
+/// Quiet period after the last event before a batch of changes is delivered
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Events that can add, change or remove a desktop entry or icon file
+const WATCH_MASK: u32 = libc::IN_CREATE
+    | libc::IN_DELETE
+    | libc::IN_CLOSE_WRITE
+    | libc::IN_MOVED_FROM
+    | libc::IN_MOVED_TO
+    | libc::IN_ATTRIB;
+
+/// Watch `roots` recursively on a background thread and call `on_change` with every
+/// debounced batch of changed paths. Roots that don't exist yet are watched from
+/// their nearest existing folder and picked up once created. Directories for which
+/// `skip` returns true are not watched. `kind` names what is watched in log messages.
+pub fn spawn<S, F>(kind: &'static str, roots: Vec<PathBuf>, skip: S, mut on_change: F)
+where
+    S: Fn(&Path) -> bool + Send + 'static,
+    F: FnMut(Vec<PathBuf>) + Send + 'static,
+{
+    let mut watcher = match Watcher::new(kind, roots, Box::new(skip)) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            logs::log_error(&format!("Failed to initialize inotify: {}", e));
+            return;
+        }
+    };
+
+    thread::spawn(move || {
+        watcher.watch_roots();
+        logs::log_info(&format!(
+            "Watching {} directories for {} changes",
+            watcher.dirs.len(),
+            kind
+        ));
+
+        loop {
+            match watcher.next_batch() {
+                Ok(paths) if paths.is_empty() => {}
+                Ok(paths) => on_change(paths),
+                Err(e) => {
+                    logs::log_error(&format!("Stopped watching {} directories: {}", kind, e));
+                    break;
+                }
+            }
+        }
+    });
+}
+
+struct Watcher {
+    fd: i32,
+    kind: &'static str,
+    roots: Vec<PathBuf>,
+    skip: Box<dyn Fn(&Path) -> bool + Send>,
+    /// Watched directory for each watch descriptor
+    dirs: HashMap<i32, PathBuf>,
+    /// Roots that don't exist yet
+    missing: Vec<PathBuf>,
+    /// Nearest existing folder above a missing root, for each watch descriptor;
+    /// only its subfolders being created matter
+    ancestors: HashMap<i32, PathBuf>,
+    /// Whether the inotify watch limit was hit, which is only logged once
+    limit_reached: bool,
+}
+
+impl Watcher {
+    fn new(
+        kind: &'static str,
+        roots: Vec<PathBuf>,
+        skip: Box<dyn Fn(&Path) -> bool + Send>,
+    ) -> io::Result<Self> {
+        let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Self {
+            fd,
+            kind,
+            roots,
+            skip,
+            dirs: HashMap::new(),
+            missing: Vec::new(),
+            ancestors: HashMap::new(),
+            limit_reached: false,
+        })
+    }
+
+    fn watch_roots(&mut self) {
+        for root in self.roots.clone() {
+            self.watch_root(&root, &mut HashSet::new());
+        }
+    }
+
+    /// Watch `root` with everything below it, or if it doesn't exist yet, the
+    /// nearest existing folder above it, so it can be watched once it's created
+    fn watch_root(&mut self, root: &Path, found: &mut HashSet<PathBuf>) {
+        if root.is_dir() {
+            self.missing.retain(|missing| missing != root);
+            self.watch_tree(root, found);
+            return;
+        }
+
+        if !self.missing.iter().any(|missing| missing == root) {
+            self.missing.push(root.to_path_buf());
+        }
+        if let Some(ancestor) = root.ancestors().skip(1).find(|dir| dir.is_dir())
+            && let Some(wd) = self.add_watch(ancestor)
+        {
+            self.ancestors.insert(wd, ancestor.to_path_buf());
+        }
+    }
+
+    /// Watch `dir` and every directory below it, collecting the files found inside.
+    /// Symlinked directories are not followed, so link cycles can't recurse forever.
+    fn watch_tree(&mut self, dir: &Path, found: &mut HashSet<PathBuf>) {
+        if (self.skip)(dir) {
+            return;
+        }
+        let Some(wd) = self.add_watch(dir) else {
+            return;
+        };
+        self.dirs.insert(wd, dir.to_path_buf());
+
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            match entry.file_type() {
+                Ok(file_type) if file_type.is_dir() => self.watch_tree(&path, found),
+                Ok(_) => {
+                    found.insert(path);
+                }
+                Err(_) => {}
+            }
+        }
+    }
+
+    /// Add an inotify watch for `dir`, returning its watch descriptor
+    fn add_watch(&mut self, dir: &Path) -> Option<i32> {
+        let c_path = CString::new(dir.as_os_str().as_bytes()).ok()?;
+
+        let wd = unsafe { libc::inotify_add_watch(self.fd, c_path.as_ptr(), WATCH_MASK) };
+        if wd < 0 {
+            let error = io::Error::last_os_error();
+            if error.raw_os_error() != Some(libc::ENOSPC) {
+                logs::log_error(&format!("Failed to watch {}: {}", dir.display(), error));
+            } else if !self.limit_reached {
+                self.limit_reached = true;
+                logs::log_error(&format!(
+                    "Reached the inotify watch limit at {}, so changes in it and further {} \
+                     directories go unnoticed; raise fs.inotify.max_user_watches or \
+                     watch fewer folders",
+                    dir.display(),
+                    self.kind
+                ));
+            }
+            return None;
+        }
+        Some(wd)
+    }
+
+    /// Block until something changes, then keep collecting events until none
+    /// arrive for `DEBOUNCE`, so a package install yields a single batch
+    fn next_batch(&mut self) -> io::Result<Vec<PathBuf>> {
+        let mut changed = HashSet::new();
+
+        self.read_events(&mut changed)?;
+        while self.wait_readable(DEBOUNCE)? {
+            self.read_events(&mut changed)?;
+        }
+
+        Ok(changed.into_iter().collect())
+    }
+
+    fn wait_readable(&self, timeout: Duration) -> io::Result<bool> {
+        let mut poll_fd = libc::pollfd {
+            fd: self.fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+
+        let ready = unsafe { libc::poll(&mut poll_fd, 1, timeout.as_millis() as i32) };
+        if ready < 0 {
+            let err = io::Error::last_os_error();
+            return if err.kind() == io::ErrorKind::Interrupted {
+                Ok(true)
+            } else {
+                Err(err)
+            };
+        }
+
+        Ok(ready > 0)
+    }
+
+    fn read_events(&mut self, changed: &mut HashSet<PathBuf>) -> io::Result<()> {
+        // Large enough for many events; the kernel never splits one across reads
+        let mut buffer = [0u8; 16 * 1024];
+        let len = unsafe { libc::read(self.fd, buffer.as_mut_ptr().cast(), buffer.len()) };
+        if len < 0 {
+            let err = io::Error::last_os_error();
+            return if err.kind() == io::ErrorKind::Interrupted {
+                Ok(())
+            } else {
+                Err(err)
+            };
+        }
+
+        let len = len as usize;
+        let header_len = mem::size_of::<libc::inotify_event>();
+        let mut offset = 0;
+
+        while offset + header_len <= len {
+            let event: libc::inotify_event =
+                unsafe { ptr::read_unaligned(buffer.as_ptr().add(offset).cast()) };
+            let name_start = offset + header_len;
+            let name_end = (name_start + event.len as usize).min(len);
+            offset = name_end;
+
+            // The name is padded with NUL bytes up to the record length
+            let name = buffer[name_start..name_end]
+                .split(|&byte| byte == 0)
+                .next()
+                .unwrap_or_default();
+
+            if event.mask & libc::IN_Q_OVERFLOW != 0 {
+                // Events were dropped; report the roots so everything is rechecked
+                logs::log_error(&format!(
+                    "inotify queue overflowed, rescanning {} directories",
+                    self.kind
+                ));
+                changed.extend(self.roots.iter().cloned());
+                for root in self.missing.clone() {
+                    self.watch_root(&root, changed);
+                }
+                continue;
+            }
+
+            if event.mask & libc::IN_IGNORED != 0 {
+                self.ancestors.remove(&event.wd);
+                // A deleted root is watched for again
+                if let Some(dir) = self.dirs.remove(&event.wd)
+                    && self.roots.contains(&dir)
+                {
+                    self.watch_root(&dir, changed);
+                }
+                continue;
+            }
+
+            let created_dir = event.mask & libc::IN_ISDIR != 0
+                && event.mask & (libc::IN_CREATE | libc::IN_MOVED_TO) != 0;
+
+            // A folder on the way to a missing root appeared; the watch moves
+            // down to it, or to the root itself if that exists by now
+            if created_dir && let Some(ancestor) = self.ancestors.get(&event.wd) {
+                let created = ancestor.join(OsStr::from_bytes(name));
+                let roots: Vec<PathBuf> = self
+                    .missing
+                    .iter()
+                    .filter(|root| root.starts_with(&created))
+                    .cloned()
+                    .collect();
+                for root in roots {
+                    self.watch_root(&root, changed);
+                }
+            }
+
+            let Some(dir) = self.dirs.get(&event.wd) else {
+                continue;
+            };
+            let path = dir.join(OsStr::from_bytes(name));
+
+            // New directories need their own watches, and files may already be
+            // inside by the time the watch is added
+            if created_dir {
+                self.watch_tree(&path, changed);
+            }
+
+            changed.insert(path);
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.fd);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("launchdock-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Every path reported until nothing happens for a moment
+    fn changes(watcher: &mut Watcher) -> HashSet<PathBuf> {
+        let mut changed = HashSet::new();
+        while watcher.wait_readable(Duration::from_millis(200)).unwrap() {
+            watcher.read_events(&mut changed).unwrap();
+        }
+        changed
+    }
+
+    #[test]
+    fn test_event_paths() {
+        let root = temp_dir("watch-events");
+        fs::create_dir_all(root.join("skipped")).unwrap();
+        let skip = Box::new(|dir: &Path| dir.ends_with("skipped"));
+        let mut watcher = Watcher::new("test", vec![root.clone()], skip).unwrap();
+        watcher.watch_roots();
+
+        // Files in new folders are found even if written before the folder is watched
+        fs::write(root.join("firefox.desktop"), "").unwrap();
+        fs::create_dir_all(root.join("kde")).unwrap();
+        fs::write(root.join("kde/konsole.desktop"), "").unwrap();
+        fs::write(root.join("skipped/ignored.desktop"), "").unwrap();
+        let changed = changes(&mut watcher);
+        assert!(changed.contains(&root.join("firefox.desktop")));
+        assert!(changed.contains(&root.join("kde")));
+        assert!(changed.contains(&root.join("kde/konsole.desktop")));
+        assert!(!changed.contains(&root.join("skipped/ignored.desktop")));
+
+        // The new folder is watched from now on
+        fs::remove_file(root.join("kde/konsole.desktop")).unwrap();
+        fs::rename(
+            root.join("firefox.desktop"),
+            root.join("kde/firefox.desktop"),
+        )
+        .unwrap();
+        let changed = changes(&mut watcher);
+        assert_eq!(
+            changed,
+            HashSet::from([
+                root.join("kde/konsole.desktop"),
+                root.join("firefox.desktop"),
+                root.join("kde/firefox.desktop"),
+            ])
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_missing_root() {
+        let base = temp_dir("watch-missing");
+        let root = base.join("flatpak/exports/share/applications");
+        let mut watcher = Watcher::new("test", vec![root.clone()], Box::new(|_| false)).unwrap();
+        watcher.watch_roots();
+        assert!(watcher.dirs.is_empty());
+
+        // Created along with its parents, as on the first Flatpak install
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("org.gimp.GIMP.desktop"), "").unwrap();
+        assert!(changes(&mut watcher).contains(&root.join("org.gimp.GIMP.desktop")));
+
+        fs::write(root.join("org.kde.krita.desktop"), "").unwrap();
+        assert!(changes(&mut watcher).contains(&root.join("org.kde.krita.desktop")));
+
+        // Removed and created again
+        fs::remove_dir_all(base.join("flatpak")).unwrap();
+        changes(&mut watcher);
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("org.gimp.GIMP.desktop"), "").unwrap();
+        assert!(changes(&mut watcher).contains(&root.join("org.gimp.GIMP.desktop")));
+
+        fs::remove_dir_all(&base).unwrap();
+    }
+}